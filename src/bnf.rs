@@ -9,19 +9,31 @@ pub(crate) struct Bnf {
   pub(crate) starts: IndexMap<String, NontermId>,
   pub(crate) nonterms: Vec<Nonterm>,
   pub(crate) prods: Vec<Production>,
+  /// the `error` pseudo-terminal and the synchronizing terminals for
+  /// panic-mode recovery, resolved from [`Grammar::recover`]
+  pub(crate) recovery: Option<Recovery>,
+  /// grammar-level precedence of each terminal: `(level, assoc)`, with a lower
+  /// level binding less tightly
+  pub(crate) token_prec: HashMap<TermId, (u16, Assoc)>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Debug)]
+pub(crate) struct Recovery {
+  pub(crate) error: TermId,
+  pub(crate) sync: Vec<TermId>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) enum Symbol {
   Term(TermId),
   Nonterm(NontermId),
 }
 
-#[derive(Clone, PartialEq, Eq, Copy, Debug)]
-pub(crate) struct TermId(pub(crate) u32);
+#[derive(Clone, PartialEq, Eq, Copy, Hash, Debug)]
+pub struct TermId(pub u32);
 
 #[derive(Clone, PartialEq, Eq, Copy, Debug, Hash, Default)]
-pub(crate) struct NontermId(pub(crate) u32);
+pub struct NontermId(pub u32);
 
 #[derive(Clone, Default, Debug)]
 pub(crate) struct Nonterm {
@@ -36,10 +48,14 @@ pub(crate) struct Production {
   pub(crate) action: ProdAction,
   pub(crate) prec: Option<u16>,
   pub(crate) assoc: Assoc,
+  /// `%prec`-style override: the terminal whose grammar-level precedence this
+  /// production borrows instead of its rightmost terminal
+  pub(crate) prec_override: Option<TermId>,
   pub(crate) symbols: Vec<Symbol>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum ProdAction {
   None,
   /// `rule*  ->  ε`
@@ -156,7 +172,7 @@ impl Bnf {
       }
     }
 
-    Bnf { tokens, starts, nonterms, prods }
+    Bnf { tokens, starts, nonterms, prods, recovery: None, token_prec: HashMap::new() }
   }
 }
 
@@ -188,11 +204,25 @@ impl From<Grammar> for Bnf {
       })
       .collect();
 
+    let recovery = grammar.recovery.map(|r| Recovery {
+      error: tokens[&r.error],
+      sync: r.sync.iter().map(|s| tokens[s]).collect(),
+    });
+
+    let mut token_prec = HashMap::new();
+    for (level, prec) in grammar.prec.iter().enumerate() {
+      for token in &prec.tokens {
+        token_prec.insert(tokens[token], (level as u16, prec.assoc));
+      }
+    }
+
     Bnf {
       tokens,
       starts,
       nonterms,
       prods,
+      recovery,
+      token_prec,
     }
   }
 }
@@ -229,7 +259,8 @@ fn gen_nonterm(
       };
       insert_nonterm(nonterms, prods, symbols, name, nonterm)
     }
-    RuleInner::Many(box RuleRep { rule }) => {
+    RuleInner::Many(rep) => {
+      let RuleRep { rule } = *rep;
       gen_rep_nonterm(nonterms, symbols, name, |nonterms, symbols, id| {
         let subrule_sym = gen_sym(nonterms, prods, symbols, rule);
         let prod_start_ix = prods.len();
@@ -255,7 +286,8 @@ fn gen_nonterm(
         prod_start_ix .. prod_start_ix + 2
       })
     }
-    RuleInner::Many1(box RuleRep { rule }) => {
+    RuleInner::Some(rep) => {
+      let RuleRep { rule } = *rep;
       gen_rep_nonterm(nonterms, symbols, name, |nonterms, symbols, id| {
         let sym = gen_sym(nonterms, prods, symbols, rule);
         let prod_start_ix = prods.len();
@@ -282,7 +314,8 @@ fn gen_nonterm(
         prod_start_ix .. prod_start_ix + 2
       })
     }
-    RuleInner::Option(box RuleRep { rule }) => {
+    RuleInner::Option(rep) => {
+      let RuleRep { rule } = *rep;
       gen_rep_nonterm(nonterms, symbols, name, |nonterms, symbols, id| {
         let mut prod = gen_prod(nonterms, prods, symbols,
           ProdAction::NonemptyOption, rule);
@@ -302,17 +335,18 @@ fn gen_nonterm(
         prod_start_ix .. prod_start_ix + 2
       })
     }
-    RuleInner::SepBy(box RuleSepBy { sep, rule }) => {
+    RuleInner::SepBy(sb) => {
+      let RuleSepBy { sep, rule } = *sb;
       let name = name.into();
-      
+
       // sepBy(sep, rule) -> sepBy1(sep, rule)
       let sep_by1 = gen_prod(
         nonterms, prods, symbols,
         ProdAction::NonemptySepBy,
-        RuleInner::SepBy1(box RuleSepBy {
+        RuleInner::SepBy1(Box::new(RuleSepBy {
           sep,
           rule,
-        }));
+        })));
 
       let nonterm = Nonterm {
         name: name.clone(),
@@ -329,7 +363,8 @@ fn gen_nonterm(
 
       insert_nonterm(nonterms, prods, symbols, name, nonterm)
     }
-    RuleInner::SepBy1(box RuleSepBy { sep, rule }) => {
+    RuleInner::SepBy1(sb) => {
+      let RuleSepBy { sep, rule } = *sb;
       gen_rep_nonterm(nonterms, symbols, name, |nonterms, symbols, id| {
         let sep_sym = gen_sym(nonterms, prods, symbols, sep);
         let sym = gen_sym(nonterms, prods, symbols, rule);
@@ -358,7 +393,8 @@ fn gen_nonterm(
         prods_start_ix .. prods_start_ix + 2
       })
     }
-    RuleInner::Prec(box RulePrec { prec, assoc, rule }) => {
+    RuleInner::Prec(p) => {
+      let RulePrec { prec, assoc, rule } = *p;
       let nonterm_id = gen_nonterm(nonterms, prods, symbols, name, rule);
       for prod_ix in &mut nonterms[nonterm_id.0 as usize].prod_range {
         prods[prod_ix].prec = Some(prec);
@@ -366,6 +402,25 @@ fn gen_nonterm(
       }
       nonterm_id
     }
+    RuleInner::PrecTok(pt) => {
+      let RulePrecTok { token, rule } = *pt;
+      let term = unwrap_term(symbols, &token);
+      let nonterm_id = gen_nonterm(nonterms, prods, symbols, name, rule);
+      for prod_ix in &mut nonterms[nonterm_id.0 as usize].prod_range {
+        prods[prod_ix].prec_override = Some(term);
+      }
+      nonterm_id
+    }
+    RuleInner::Apply(_) => {
+      unreachable!("template applications are expanded before lowering")
+    }
+  }
+}
+
+fn unwrap_term(symbols: &HashMap<String, Symbol>, name: &str) -> TermId {
+  match symbols[name] {
+    Symbol::Term(id) => id,
+    Symbol::Nonterm(_) => panic!("'{}' is not a terminal", name),
   }
 }
 
@@ -439,12 +494,23 @@ fn gen_prod(
         ..Default::default()
       }
     }
-    RuleInner::Prec(box RulePrec { prec, assoc, rule }) => {
+    RuleInner::Prec(p) => {
+      let RulePrec { prec, assoc, rule } = *p;
       let mut prod = gen_prod(nonterms, prods, symbols, action, rule);
       prod.prec = Some(prec);
       prod.assoc = assoc;
       prod
     }
+    RuleInner::PrecTok(pt) => {
+      let RulePrecTok { token, rule } = *pt;
+      let term = unwrap_term(symbols, &token);
+      let mut prod = gen_prod(nonterms, prods, symbols, action, rule);
+      prod.prec_override = Some(term);
+      prod
+    }
+    RuleInner::Apply(_) => {
+      unreachable!("template applications are expanded before lowering")
+    }
     _ => {
       Production {
         action,
@@ -468,7 +534,8 @@ fn gen_sym(
       }
       symbols[&sym]
     },
-    RuleInner::Prec(box RulePrec { prec, assoc, rule }) => {
+    RuleInner::Prec(p) => {
+      let RulePrec { prec, assoc, rule } = *p;
       let id = match gen_sym(nonterms, prods, symbols, rule.clone()) {
         Symbol::Term(_) => {
           gen_nonterm(nonterms, prods, symbols, rule.name(), rule)
@@ -481,6 +548,23 @@ fn gen_sym(
       }
       Symbol::Nonterm(id)
     }
+    RuleInner::PrecTok(pt) => {
+      let RulePrecTok { token, rule } = *pt;
+      let term = unwrap_term(symbols, &token);
+      let id = match gen_sym(nonterms, prods, symbols, rule.clone()) {
+        Symbol::Term(_) => {
+          gen_nonterm(nonterms, prods, symbols, rule.name(), rule)
+        }
+        Symbol::Nonterm(id) => id,
+      };
+      for prod_ix in &mut nonterms[id.0 as usize].prod_range {
+        prods[prod_ix].prec_override = Some(term);
+      }
+      Symbol::Nonterm(id)
+    }
+    RuleInner::Apply(_) => {
+      unreachable!("template applications are expanded before lowering")
+    }
     _ => {
       let name = rule.name();
       Symbol::Nonterm(gen_nonterm(nonterms, prods, symbols, name, rule))
@@ -0,0 +1,392 @@
+//! A textual EBNF front-end: [`parse_grammar`] reads a grammar definition from
+//! source text and produces the same [`Grammar`] the `sym`/`seq`/`many`/… combinators
+//! would, so grammars can live in external files instead of being recompiled
+//! into the program.
+//!
+//! The concrete syntax is a small, `;`-terminated item language:
+//!
+//! ```text
+//! token "+" "-" "*" "/" num "(" ")" id "," ;
+//! start expr ;
+//! left "+" "-" ;
+//! left "*" "/" ;
+//!
+//! expr =
+//!     expr ( "+" | "-" ) expr
+//!   | expr ( "*" | "/" ) expr
+//!   | "-" expr            %prec "*"
+//!   | "(" expr ")"
+//!   | sep_by( ",", id )
+//!   | id
+//!   | num
+//!   ;
+//! ```
+//!
+//! Terminals and nonterminals are written as bare identifiers or double-quoted
+//! strings; `*`/`+`/`?` are postfix repetition, `|` alternation, parentheses
+//! group, and `sep_by(sep, rule)` / `sep_by1(sep, rule)` build separated lists.
+//! `left`/`right`/`nonassoc` items declare precedence levels lowest-binding
+//! first, and a `%prec tok` suffix overrides a production's precedence. Semantic
+//! checks are delegated to [`Grammar::validate`].
+
+use indexmap::IndexMap;
+use crate::grammar::*;
+
+/// Parses a grammar from EBNF source text.
+pub fn parse_grammar(src: &str) -> Result<Grammar, String> {
+  let tokens = lex(src)?;
+  Parser::new(tokens).grammar()
+}
+
+/// A lexeme plus the byte offset where it started, for diagnostics.
+#[derive(Clone, Debug)]
+struct Spanned {
+  tok: Tok,
+  pos: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Tok {
+  /// a bare identifier or a double-quoted string; both name a symbol
+  Name(String),
+  Eq,
+  Bar,
+  LParen,
+  RParen,
+  Comma,
+  Star,
+  Plus,
+  Question,
+  Semi,
+  /// `%prec`
+  Prec,
+}
+
+fn lex(src: &str) -> Result<Vec<Spanned>, String> {
+  let bytes = src.as_bytes();
+  let mut toks = vec![];
+  let mut i = 0;
+
+  while i < bytes.len() {
+    let c = bytes[i];
+    match c {
+      b' ' | b'\t' | b'\r' | b'\n' => i += 1,
+      b'/' if bytes.get(i + 1) == Some(&b'/') => {
+        while i < bytes.len() && bytes[i] != b'\n' {
+          i += 1;
+        }
+      }
+      b'=' => { toks.push(Spanned { tok: Tok::Eq, pos: i }); i += 1; }
+      b'|' => { toks.push(Spanned { tok: Tok::Bar, pos: i }); i += 1; }
+      b'(' => { toks.push(Spanned { tok: Tok::LParen, pos: i }); i += 1; }
+      b')' => { toks.push(Spanned { tok: Tok::RParen, pos: i }); i += 1; }
+      b',' => { toks.push(Spanned { tok: Tok::Comma, pos: i }); i += 1; }
+      b'*' => { toks.push(Spanned { tok: Tok::Star, pos: i }); i += 1; }
+      b'+' => { toks.push(Spanned { tok: Tok::Plus, pos: i }); i += 1; }
+      b'?' => { toks.push(Spanned { tok: Tok::Question, pos: i }); i += 1; }
+      b';' => { toks.push(Spanned { tok: Tok::Semi, pos: i }); i += 1; }
+      b'%' => {
+        let start = i;
+        i += 1;
+        let name_start = i;
+        while i < bytes.len() && is_ident_byte(bytes[i]) {
+          i += 1;
+        }
+        let word = &src[name_start..i];
+        if word == "prec" {
+          toks.push(Spanned { tok: Tok::Prec, pos: start });
+        } else {
+          return Err(format!("unknown directive '%{}' at byte {}", word, start));
+        }
+      }
+      b'"' => {
+        let start = i;
+        i += 1;
+        let content_start = i;
+        while i < bytes.len() && bytes[i] != b'"' {
+          i += 1;
+        }
+        if i >= bytes.len() {
+          return Err(format!("unterminated string at byte {}", start));
+        }
+        let s = src[content_start..i].to_owned();
+        i += 1;
+        toks.push(Spanned { tok: Tok::Name(s), pos: start });
+      }
+      _ if is_ident_byte(c) => {
+        let start = i;
+        while i < bytes.len() && is_ident_byte(bytes[i]) {
+          i += 1;
+        }
+        toks.push(Spanned { tok: Tok::Name(src[start..i].to_owned()), pos: start });
+      }
+      _ => return Err(format!("unexpected character '{}' at byte {}", c as char, i)),
+    }
+  }
+
+  Ok(toks)
+}
+
+fn is_ident_byte(c: u8) -> bool {
+  c.is_ascii_alphanumeric() || c == b'_'
+}
+
+/// Recursive-descent parser over the lexed EBNF.
+struct Parser {
+  toks: Vec<Spanned>,
+  pos: usize,
+}
+
+impl Parser {
+  fn new(toks: Vec<Spanned>) -> Self {
+    Parser { toks, pos: 0 }
+  }
+
+  /// Parses the whole document into a validated [`Grammar`].
+  fn grammar(mut self) -> Result<Grammar, String> {
+    let mut tokens = vec![];
+    let mut start = vec![];
+    let mut prec = vec![];
+    let mut rules = IndexMap::new();
+
+    while self.pos < self.toks.len() {
+      match self.peek_name() {
+        Some("token") => {
+          self.advance();
+          tokens.extend(self.names()?);
+          self.expect(Tok::Semi)?;
+        }
+        Some("start") => {
+          self.advance();
+          start.extend(self.names()?);
+          self.expect(Tok::Semi)?;
+        }
+        Some(kw @ ("left" | "right" | "nonassoc")) => {
+          self.advance();
+          let assoc = match kw {
+            "left" => Assoc::Left,
+            "right" => Assoc::Right,
+            _ => Assoc::None,
+          };
+          prec.push(PrecLevel { assoc, tokens: self.names()? });
+          self.expect(Tok::Semi)?;
+        }
+        Some(_) => {
+          let (name, rule) = self.rule()?;
+          if rules.insert(name.clone(), rule).is_some() {
+            return Err(format!("duplicate rule '{}'", name));
+          }
+        }
+        None => {
+          return Err(format!("expected an item at byte {}", self.cur_pos()));
+        }
+      }
+    }
+
+    let grammar = Grammar { tokens, start, rules, recovery: None, prec, templates: IndexMap::new() };
+    grammar.validate()?;
+    Ok(grammar)
+  }
+
+  /// `name ( '=' alt ';' )` — a single rule definition.
+  fn rule(&mut self) -> Result<(String, Rule), String> {
+    let name = self.name()?;
+    self.expect(Tok::Eq)?;
+    let alt = self.alt()?;
+    self.expect(Tok::Semi)?;
+    Ok((name, Rule(alt)))
+  }
+
+  /// `seq ( '|' seq )*`
+  fn alt(&mut self) -> Result<RuleInner, String> {
+    let mut alts = vec![self.seq()?];
+    while self.eat(Tok::Bar) {
+      alts.push(self.seq()?);
+    }
+    Ok(if alts.len() == 1 {
+      alts.pop().unwrap()
+    } else {
+      RuleInner::Or(alts)
+    })
+  }
+
+  /// `postfix* ( '%prec' name )?` — an empty sequence is the epsilon
+  /// production, as in a `a | ` style alternative.
+  fn seq(&mut self) -> Result<RuleInner, String> {
+    let mut items = vec![];
+    while self.starts_primary() {
+      items.push(self.postfix()?);
+    }
+    let mut rule = if items.len() == 1 {
+      items.pop().unwrap()
+    } else {
+      RuleInner::Seq(items)
+    };
+    if self.eat(Tok::Prec) {
+      let token = self.name()?;
+      rule = RuleInner::PrecTok(Box::new(RulePrecTok { token, rule }));
+    }
+    Ok(rule)
+  }
+
+  /// `primary ( '*' | '+' | '?' )*`
+  fn postfix(&mut self) -> Result<RuleInner, String> {
+    let mut rule = self.primary()?;
+    loop {
+      rule = if self.eat(Tok::Star) {
+        RuleInner::Many(Box::new(RuleRep { rule }))
+      } else if self.eat(Tok::Plus) {
+        RuleInner::Some(Box::new(RuleRep { rule }))
+      } else if self.eat(Tok::Question) {
+        RuleInner::Option(Box::new(RuleRep { rule }))
+      } else {
+        break;
+      };
+    }
+    Ok(rule)
+  }
+
+  /// `name | '(' alt ')' | ('sep_by'|'sep_by1') '(' alt ',' alt ')'`
+  fn primary(&mut self) -> Result<RuleInner, String> {
+    match self.peek_name() {
+      Some(kw @ ("sep_by" | "sep_by1")) => {
+        let is1 = kw == "sep_by1";
+        self.advance();
+        self.expect(Tok::LParen)?;
+        let sep = self.alt()?;
+        self.expect(Tok::Comma)?;
+        let rule = self.alt()?;
+        self.expect(Tok::RParen)?;
+        let inner = Box::new(RuleSepBy { sep, rule });
+        Ok(if is1 { RuleInner::SepBy1(inner) } else { RuleInner::SepBy(inner) })
+      }
+      Some(_) => Ok(RuleInner::Sym(self.name()?)),
+      None if self.eat(Tok::LParen) => {
+        let alt = self.alt()?;
+        self.expect(Tok::RParen)?;
+        Ok(alt)
+      }
+      None => Err(format!("expected a symbol or '(' at byte {}", self.cur_pos())),
+    }
+  }
+
+  /// Whether the cursor is at the start of a primary (for greedy seq parsing).
+  fn starts_primary(&self) -> bool {
+    matches!(self.toks.get(self.pos).map(|s| &s.tok),
+      Some(Tok::Name(_)) | Some(Tok::LParen))
+  }
+
+  /// One-or-more symbol names, used by the `token`/`start`/precedence items.
+  fn names(&mut self) -> Result<Vec<String>, String> {
+    let mut names = vec![self.name()?];
+    while matches!(self.toks.get(self.pos).map(|s| &s.tok), Some(Tok::Name(_))) {
+      names.push(self.name()?);
+    }
+    Ok(names)
+  }
+
+  fn name(&mut self) -> Result<String, String> {
+    match self.toks.get(self.pos) {
+      Some(Spanned { tok: Tok::Name(s), .. }) => {
+        let s = s.clone();
+        self.pos += 1;
+        Ok(s)
+      }
+      _ => Err(format!("expected a name at byte {}", self.cur_pos())),
+    }
+  }
+
+  fn peek_name(&self) -> Option<&str> {
+    match self.toks.get(self.pos) {
+      Some(Spanned { tok: Tok::Name(s), .. }) => Some(s.as_str()),
+      _ => None,
+    }
+  }
+
+  fn advance(&mut self) {
+    self.pos += 1;
+  }
+
+  fn eat(&mut self, tok: Tok) -> bool {
+    if self.toks.get(self.pos).map(|s| &s.tok) == Some(&tok) {
+      self.pos += 1;
+      true
+    } else {
+      false
+    }
+  }
+
+  fn expect(&mut self, tok: Tok) -> Result<(), String> {
+    if self.eat(tok.clone()) {
+      Ok(())
+    } else {
+      Err(format!("expected {:?} at byte {}", tok, self.cur_pos()))
+    }
+  }
+
+  fn cur_pos(&self) -> usize {
+    self.toks.get(self.pos).map(|s| s.pos)
+      .unwrap_or_else(|| self.toks.last().map_or(0, |s| s.pos))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_expr_grammar() {
+    let src = r#"
+      token "+" "-" "*" "/" num "(" ")" id "," ;
+      start expr ;
+      left "+" "-" ;
+      left "*" "/" ;
+
+      expr =
+          expr ( "+" | "-" ) expr
+        | expr ( "*" | "/" ) expr
+        | "-" expr            %prec "*"
+        | "(" expr ")"
+        | call
+        | id
+        | num
+        ;
+
+      call = id "(" sep_by( ",", expr ) ")" ;
+    "#;
+
+    let gram = parse_grammar(src).unwrap();
+    assert_eq!(gram.tokens.len(), 9);
+    assert_eq!(gram.start, vec!["expr".to_owned()]);
+    assert_eq!(gram.prec.len(), 2);
+    assert!(gram.rules.contains_key("expr"));
+    assert!(gram.rules.contains_key("call"));
+  }
+
+  #[test]
+  fn parses_epsilon_alternative() {
+    let src = r#"
+      token a ;
+      start opt ;
+      opt = a | ;
+    "#;
+    let gram = parse_grammar(src).unwrap();
+    match &gram.rules["opt"].0 {
+      RuleInner::Or(alts) => {
+        assert_eq!(alts.len(), 2);
+        assert!(matches!(&alts[1], RuleInner::Seq(s) if s.is_empty()));
+      }
+      other => panic!("expected an alternation, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn reports_undefined_symbol() {
+    let src = r#"
+      token a ;
+      start s ;
+      s = a b ;
+    "#;
+    assert!(parse_grammar(src).is_err());
+  }
+}
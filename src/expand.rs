@@ -0,0 +1,249 @@
+//! Expansion pass for parameterized rule templates.
+//!
+//! A template like `Delimited<Elem, Sep>` registered with
+//! [`Grammar::template`](crate::Grammar::template) is instantiated by an
+//! [`apply`](crate::grammar::apply) site in a rule body. This pass walks every
+//! rule, replaces each `apply` with a reference to a freshly synthesized
+//! concrete rule, and splices that rule into `rules`. Identical instantiations
+//! are memoized so `Delimited<Expr, Comma>` used in ten places yields a single
+//! generated nonterminal, and a self-referential template (e.g. `List<T> -> ε |
+//! List<T> T`) terminates because the memo entry is recorded before its body is
+//! expanded. The resulting grammar is template-free and ready for
+//! [`validate`](crate::Grammar::validate) and lowering.
+
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use crate::grammar::*;
+
+/// A generous ceiling on synthesized instantiations; crossing it means the
+/// templates recurse with ever-growing arguments and would never terminate.
+const MAX_INSTANTIATIONS: usize = 10_000;
+
+pub(crate) fn expand(grammar: &Grammar) -> Result<Grammar, String> {
+  let mut expander = Expander {
+    templates: &grammar.templates,
+    generated: IndexMap::new(),
+    memo: HashMap::new(),
+  };
+
+  let mut rules = IndexMap::new();
+  for (name, rule) in &grammar.rules {
+    let body = expander.rewrite(&rule.0)?;
+    rules.insert(name.clone(), Rule(body));
+  }
+  rules.extend(expander.generated.into_iter().map(|(n, r)| (n, Rule(r))));
+
+  Ok(Grammar {
+    tokens: grammar.tokens.clone(),
+    start: grammar.start.clone(),
+    rules,
+    recovery: grammar.recovery.clone(),
+    prec: grammar.prec.clone(),
+    templates: IndexMap::new(),
+  })
+}
+
+struct Expander<'a> {
+  templates: &'a IndexMap<String, Template>,
+  /// synthesized concrete rules, keyed by their generated name, appended to the
+  /// grammar after the originals
+  generated: IndexMap<String, RuleInner>,
+  /// instantiation signature -> generated rule name
+  memo: HashMap<String, String>,
+}
+
+impl<'a> Expander<'a> {
+  /// Rewrites a rule, replacing every template application by a symbol
+  /// referencing its synthesized rule.
+  fn rewrite(&mut self, rule: &RuleInner) -> Result<RuleInner, String> {
+    Ok(match rule {
+      RuleInner::Sym(s) => RuleInner::Sym(s.clone()),
+      RuleInner::Seq(rules) => RuleInner::Seq(self.rewrite_all(rules)?),
+      RuleInner::Or(rules) => RuleInner::Or(self.rewrite_all(rules)?),
+      RuleInner::Many(r) => RuleInner::Many(Box::new(RuleRep { rule: self.rewrite(&r.rule)? })),
+      RuleInner::Some(r) => RuleInner::Some(Box::new(RuleRep { rule: self.rewrite(&r.rule)? })),
+      RuleInner::Option(r) => RuleInner::Option(Box::new(RuleRep { rule: self.rewrite(&r.rule)? })),
+      RuleInner::SepBy(r) => RuleInner::SepBy(Box::new(RuleSepBy {
+        sep: self.rewrite(&r.sep)?,
+        rule: self.rewrite(&r.rule)?,
+      })),
+      RuleInner::SepBy1(r) => RuleInner::SepBy1(Box::new(RuleSepBy {
+        sep: self.rewrite(&r.sep)?,
+        rule: self.rewrite(&r.rule)?,
+      })),
+      RuleInner::Prec(r) => RuleInner::Prec(Box::new(RulePrec {
+        prec: r.prec,
+        assoc: r.assoc,
+        rule: self.rewrite(&r.rule)?,
+      })),
+      RuleInner::PrecTok(r) => RuleInner::PrecTok(Box::new(RulePrecTok {
+        token: r.token.clone(),
+        rule: self.rewrite(&r.rule)?,
+      })),
+      RuleInner::Apply(apply) => self.instantiate(apply)?,
+    })
+  }
+
+  fn rewrite_all(&mut self, rules: &[RuleInner]) -> Result<Vec<RuleInner>, String> {
+    rules.iter().map(|r| self.rewrite(r)).collect()
+  }
+
+  /// Instantiates one template application, returning a `Sym` referencing the
+  /// synthesized concrete rule.
+  fn instantiate(&mut self, apply: &RuleApply) -> Result<RuleInner, String> {
+    // arguments are themselves rewritten first, so nested applications resolve
+    // to plain symbols before they enter the signature
+    let args = self.rewrite_all(&apply.args)?;
+    let sig = signature(&apply.template, &args);
+
+    if let Some(name) = self.memo.get(&sig) {
+      return Ok(RuleInner::Sym(name.clone()));
+    }
+
+    let template = self.templates.get(&apply.template)
+      .ok_or_else(|| format!("undefined template '{}'", apply.template))?;
+    if template.params.len() != args.len() {
+      return Err(format!(
+        "template '{}' expects {} argument(s), got {}",
+        apply.template, template.params.len(), args.len()));
+    }
+    // guard on instantiations *started* (`memo`), not finished (`generated`):
+    // `generated` is only populated after the recursive `rewrite` below returns,
+    // so an ever-growing-argument recursion would never trip a `generated`-based
+    // backstop and would overflow the stack instead.
+    if self.memo.len() >= MAX_INSTANTIATIONS {
+      return Err(format!(
+        "template expansion did not terminate (possible unbounded recursion \
+         through '{}')", apply.template));
+    }
+
+    // record the name before expanding the body, so a self-application with the
+    // same arguments resolves to this rule instead of recursing forever
+    let name = sig.clone();
+    self.memo.insert(sig, name.clone());
+
+    let subst = template.params.iter().cloned()
+      .zip(args.into_iter())
+      .collect::<HashMap<_, _>>();
+    let body = substitute(&template.body, &subst);
+    let body = self.rewrite(&body)?;
+    self.generated.insert(name.clone(), body);
+
+    Ok(RuleInner::Sym(name))
+  }
+}
+
+/// Substitutes parameter symbols in a template body with their argument rules.
+fn substitute(rule: &RuleInner, subst: &HashMap<String, RuleInner>) -> RuleInner {
+  match rule {
+    RuleInner::Sym(s) => subst.get(s).cloned().unwrap_or_else(|| RuleInner::Sym(s.clone())),
+    RuleInner::Seq(rules) => RuleInner::Seq(substitute_all(rules, subst)),
+    RuleInner::Or(rules) => RuleInner::Or(substitute_all(rules, subst)),
+    RuleInner::Many(r) => RuleInner::Many(Box::new(RuleRep { rule: substitute(&r.rule, subst) })),
+    RuleInner::Some(r) => RuleInner::Some(Box::new(RuleRep { rule: substitute(&r.rule, subst) })),
+    RuleInner::Option(r) => RuleInner::Option(Box::new(RuleRep { rule: substitute(&r.rule, subst) })),
+    RuleInner::SepBy(r) => RuleInner::SepBy(Box::new(RuleSepBy {
+      sep: substitute(&r.sep, subst),
+      rule: substitute(&r.rule, subst),
+    })),
+    RuleInner::SepBy1(r) => RuleInner::SepBy1(Box::new(RuleSepBy {
+      sep: substitute(&r.sep, subst),
+      rule: substitute(&r.rule, subst),
+    })),
+    RuleInner::Prec(r) => RuleInner::Prec(Box::new(RulePrec {
+      prec: r.prec,
+      assoc: r.assoc,
+      rule: substitute(&r.rule, subst),
+    })),
+    RuleInner::PrecTok(r) => RuleInner::PrecTok(Box::new(RulePrecTok {
+      token: r.token.clone(),
+      rule: substitute(&r.rule, subst),
+    })),
+    RuleInner::Apply(r) => RuleInner::Apply(Box::new(RuleApply {
+      template: r.template.clone(),
+      args: substitute_all(&r.args, subst),
+    })),
+  }
+}
+
+fn substitute_all(rules: &[RuleInner], subst: &HashMap<String, RuleInner>) -> Vec<RuleInner> {
+  rules.iter().map(|r| substitute(r, subst)).collect()
+}
+
+/// A canonical, collision-free name for an instantiation, doubling as the
+/// generated rule's name: `Delimited<expr, comma>`.
+fn signature(template: &str, args: &[RuleInner]) -> String {
+  let args = args.iter().map(render).collect::<Vec<_>>();
+  format!("{}<{}>", template, args.join(", "))
+}
+
+fn render(rule: &RuleInner) -> String {
+  match rule {
+    RuleInner::Sym(s) => s.clone(),
+    RuleInner::Seq(rules) => format!("({})", render_all(rules, " ")),
+    RuleInner::Or(rules) => format!("({})", render_all(rules, " | ")),
+    RuleInner::Many(r) => format!("{}*", render(&r.rule)),
+    RuleInner::Some(r) => format!("{}+", render(&r.rule)),
+    RuleInner::Option(r) => format!("{}?", render(&r.rule)),
+    RuleInner::SepBy(r) => format!("sep_by({}, {})", render(&r.sep), render(&r.rule)),
+    RuleInner::SepBy1(r) => format!("sep_by1({}, {})", render(&r.sep), render(&r.rule)),
+    RuleInner::Prec(r) => format!("prec({}, {:?}, {})", r.prec, r.assoc, render(&r.rule)),
+    RuleInner::PrecTok(r) => format!("{} %prec {}", render(&r.rule), r.token),
+    RuleInner::Apply(r) => signature(&r.template, &r.args),
+  }
+}
+
+fn render_all(rules: &[RuleInner], sep: &str) -> String {
+  rules.iter().map(render).collect::<Vec<_>>().join(sep)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn memoizes_identical_instantiations() {
+    let gram = grammar(
+      &[",", "a", "b"],
+      &["S"],
+      &[
+        ("S", seq([apply("Comma", vec![sym("a")]), apply("Comma", vec![sym("a")])])),
+      ]).unwrap()
+      .template("Comma", &["T"], sep_by(sym(","), sym("T")));
+
+    let expanded = gram.expand().unwrap();
+    // the two identical applications collapse to one generated rule
+    let generated = expanded.rules.keys()
+      .filter(|name| name.starts_with("Comma<"))
+      .count();
+    assert_eq!(generated, 1);
+    expanded.validate().unwrap();
+  }
+
+  #[test]
+  fn distinct_arguments_generate_distinct_rules() {
+    let gram = grammar(
+      &[",", "a", "b"],
+      &["S"],
+      &[
+        ("S", seq([apply("Comma", vec![sym("a")]), apply("Comma", vec![sym("b")])])),
+      ]).unwrap()
+      .template("Comma", &["T"], sep_by(sym(","), sym("T")));
+
+    let expanded = gram.expand().unwrap();
+    let generated = expanded.rules.keys()
+      .filter(|name| name.starts_with("Comma<"))
+      .count();
+    assert_eq!(generated, 2);
+  }
+
+  #[test]
+  fn rejects_arity_mismatch() {
+    let gram = grammar(
+      &[",", "a"],
+      &["S"],
+      &[("S", apply("Comma", vec![sym("a"), sym(",")]))]).unwrap()
+      .template("Comma", &["T"], sep_by(sym(","), sym("T")));
+    assert!(gram.expand().is_err());
+  }
+}
\ No newline at end of file
@@ -2,16 +2,54 @@ use std::collections::HashSet;
 use indexmap::IndexMap;
 use std::ops::BitOr;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Grammar {
   pub(crate) tokens: Vec<String>,
   pub(crate) start: Vec<String>,
   pub(crate) rules: IndexMap<String, Rule>,
+  pub(crate) recovery: Option<Recovery>,
+  /// yacc-style precedence levels, lowest-binding first; the table builder uses
+  /// them to resolve shift/reduce conflicts
+  pub(crate) prec: Vec<PrecLevel>,
+  /// parameterized rule templates, instantiated into concrete rules by the
+  /// expansion pass before lowering
+  pub(crate) templates: IndexMap<String, Template>,
 }
 
+/// A parameterized rule template: its formal parameter names and a body that
+/// references them as ordinary [`RuleInner::Sym`]s.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Template {
+  pub(crate) params: Vec<String>,
+  pub(crate) body: RuleInner,
+}
+
+/// One precedence level: the terminals that share it and how they associate.
+/// A level's binding strength is its position in [`Grammar::prec`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrecLevel {
+  pub assoc: Assoc,
+  pub tokens: Vec<String>,
+}
+
+/// Panic-mode error-recovery configuration: the `error` pseudo-terminal a rule
+/// shifts over to resynchronize, plus the synchronizing terminals the driver
+/// discards input up to after an error.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Recovery {
+  pub(crate) error: String,
+  pub(crate) sync: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rule(pub(crate) RuleInner);
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum RuleInner {
   Sym(String),
   Seq(Vec<RuleInner>),
@@ -22,27 +60,78 @@ pub(crate) enum RuleInner {
   SepBy(Box<RuleSepBy>),
   SepBy1(Box<RuleSepBy>),
   Prec(Box<RulePrec>),
+  PrecTok(Box<RulePrecTok>),
+  Apply(Box<RuleApply>),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct RuleRep {
   pub(crate) rule: RuleInner,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct RuleSepBy {
   pub(crate) sep: RuleInner,
   pub(crate) rule: RuleInner,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct RulePrec {
   pub(crate) prec: u16,
   pub(crate) assoc: Assoc,
   pub(crate) rule: RuleInner,
 }
 
+/// A `%prec`-style override: the production takes its precedence from `token`
+/// rather than from its rightmost terminal.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct RulePrecTok {
+  pub(crate) token: String,
+  pub(crate) rule: RuleInner,
+}
+
+/// An instantiation of a [`Template`]: the template name and the argument rules
+/// substituted for its parameters.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct RuleApply {
+  pub(crate) template: String,
+  pub(crate) args: Vec<RuleInner>,
+}
+
+impl RuleInner {
+  /// A deterministic, structurally derived name for an anonymous sub-rule that
+  /// lowering hoists into its own nonterminal. Two rules with the same shape
+  /// get the same name, so an operator used twice lowers to a single
+  /// nonterminal.
+  pub(crate) fn name(&self) -> String {
+    match self {
+      RuleInner::Sym(s) => s.clone(),
+      RuleInner::Seq(rules) => format!("({})", join_names(rules, " ")),
+      RuleInner::Or(rules) => format!("({})", join_names(rules, " | ")),
+      RuleInner::Many(r) => format!("{}*", r.rule.name()),
+      RuleInner::Some(r) => format!("{}+", r.rule.name()),
+      RuleInner::Option(r) => format!("{}?", r.rule.name()),
+      RuleInner::SepBy(r) => format!("sep_by({}, {})", r.sep.name(), r.rule.name()),
+      RuleInner::SepBy1(r) => format!("sep_by1({}, {})", r.sep.name(), r.rule.name()),
+      RuleInner::Prec(r) => r.rule.name(),
+      RuleInner::PrecTok(r) => r.rule.name(),
+      RuleInner::Apply(r) => format!(
+        "{}<{}>", r.template, join_names(&r.args, ", ")),
+    }
+  }
+}
+
+fn join_names(rules: &[RuleInner], sep: &str) -> String {
+  rules.iter().map(RuleInner::name).collect::<Vec<_>>().join(sep)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Assoc {
   None,
   Left,
@@ -64,45 +153,45 @@ pub fn seq<const N: usize>(
 pub fn many(
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::Many(box RuleRep {
+  Rule(RuleInner::Many(Box::new(RuleRep {
     rule: rule.0,
-  }))
+  })))
 }
 
 pub fn some(
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::Some(box RuleRep {
+  Rule(RuleInner::Some(Box::new(RuleRep {
     rule: rule.0,
-  }))
+  })))
 }
 
 pub fn option(
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::Option(box RuleRep {
+  Rule(RuleInner::Option(Box::new(RuleRep {
     rule: rule.0,
-  }))
+  })))
 }
 
 pub fn sep_by(
   sep: Rule,
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::SepBy(box RuleSepBy {
+  Rule(RuleInner::SepBy(Box::new(RuleSepBy {
     sep: sep.0,
     rule: rule.0,
-  }))
+  })))
 }
 
 pub fn sep_by1(
   sep: Rule,
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::SepBy1(box RuleSepBy {
+  Rule(RuleInner::SepBy1(Box::new(RuleSepBy {
     sep: sep.0,
     rule: rule.0,
-  }))
+  })))
 }
 
 pub fn prec(
@@ -110,11 +199,33 @@ pub fn prec(
   assoc: Assoc,
   rule: Rule,
 ) -> Rule {
-  Rule(RuleInner::Prec(box RulePrec {
+  Rule(RuleInner::Prec(Box::new(RulePrec {
     prec,
     assoc,
     rule: rule.0,
-  }))
+  })))
+}
+
+pub fn prec_tok(
+  token: impl Into<String>,
+  rule: Rule,
+) -> Rule {
+  Rule(RuleInner::PrecTok(Box::new(RulePrecTok {
+    token: token.into(),
+    rule: rule.0,
+  })))
+}
+
+/// Instantiates the template `name` with `args`, e.g. `apply("Delimited",
+/// vec![sym("expr"), sym(",")])`.
+pub fn apply(
+  name: impl Into<String>,
+  args: Vec<Rule>,
+) -> Rule {
+  Rule(RuleInner::Apply(Box::new(RuleApply {
+    template: name.into(),
+    args: args.into_iter().map(|r| r.0).collect(),
+  })))
 }
 
 impl BitOr for Rule {
@@ -145,6 +256,17 @@ pub fn grammar(
   tokens: &[&str],
   start: &[&str],
   rules: &[(&str, Rule)],
+) -> Result<Grammar, String> {
+  grammar_with_prec(tokens, start, &[], rules)
+}
+
+/// Like [`grammar`], but with a yacc-style precedence table: `prec` lists the
+/// levels lowest-binding first, each an `(assoc, tokens)` pair.
+pub fn grammar_with_prec(
+  tokens: &[&str],
+  start: &[&str],
+  prec: &[(Assoc, &[&str])],
+  rules: &[(&str, Rule)],
 ) -> Result<Grammar, String> {
   let rules_map = rules.iter()
     .map(|(name, rule)| ((*name).to_owned(), rule.clone()))
@@ -152,15 +274,69 @@ pub fn grammar(
   if rules_map.len() != rules.len() {
     return Err(format!("duplicate rule found in rule list"));
   }
-  
+
   Ok(Grammar {
     tokens: tokens.iter().map(|&s| s.to_owned()).collect(),
     start: start.iter().map(|&s| s.to_owned()).collect(),
     rules: rules_map,
+    recovery: None,
+    prec: prec.iter()
+      .map(|(assoc, tokens)| PrecLevel {
+        assoc: *assoc,
+        tokens: tokens.iter().map(|&s| s.to_owned()).collect(),
+      })
+      .collect(),
+    templates: IndexMap::new(),
   })
 }
 
 impl Grammar {
+  /// Enables panic-mode error recovery: `error` names the pseudo-terminal that
+  /// recovering productions (e.g. `stmt -> error ";"`) shift over, and `sync`
+  /// lists the synchronizing terminals the driver discards input up to after an
+  /// unexpected token. Both must be declared tokens.
+  pub fn recover(mut self, error: impl Into<String>, sync: &[&str]) -> Self {
+    self.recovery = Some(Recovery {
+      error: error.into(),
+      sync: sync.iter().map(|&s| s.to_owned()).collect(),
+    });
+    self
+  }
+
+  /// Registers a parameterized rule template. `body` references the parameters
+  /// in `params` as ordinary symbols; [`apply`] instantiates it with concrete
+  /// rules. Instantiations are expanded into uniquely-named concrete rules by
+  /// [`expand`](Self::expand) before validation.
+  pub fn template(mut self, name: impl Into<String>, params: &[&str], body: Rule) -> Self {
+    self.templates.insert(name.into(), Template {
+      params: params.iter().map(|&s| s.to_owned()).collect(),
+      body: body.0,
+    });
+    self
+  }
+
+  /// Expands every template instantiation into concrete rules, returning a
+  /// template-free grammar. [`Parser::new`](crate::Parser) runs this before
+  /// [`validate`](Self::validate).
+  pub fn expand(&self) -> Result<Grammar, String> {
+    crate::expand::expand(self)
+  }
+
+  /// Lowers every EBNF operator (`*`/`+`/`?`/`sepBy`) into a core grammar whose
+  /// rules use only [`RuleInner::Sym`], [`RuleInner::Seq`], and [`RuleInner::Or`]
+  /// (precedence annotations are kept around their bodies), returning the
+  /// rewritten grammar alongside a map from each synthetic
+  /// nonterminal back to the construct it replaced.
+  ///
+  /// This is a standalone view for tools that want an un-augmented core BNF;
+  /// [`Parser::new`](crate::Parser) does *not* use it, because the backend
+  /// lowering in `bnf` additionally records the `ProdAction` metadata the
+  /// typed-CST layer needs to re-sugar trees — information this pass
+  /// intentionally does not carry.
+  pub fn to_core(&self) -> crate::normalize::Normalized {
+    crate::normalize::normalize(self)
+  }
+
   pub(crate) fn validate(&self) -> Result<(), String> {
     if self.tokens.is_empty() {
       return Err(format!("token list is empty"));
@@ -204,10 +380,51 @@ impl Grammar {
       rule.0.validate(&names)?;
     }
 
+    if let Some(recovery) = &self.recovery {
+      if !token_names.contains(recovery.error.as_str()) {
+        return Err(format!("error terminal '{}' is undefined", recovery.error));
+      }
+      for sync in &recovery.sync {
+        if !token_names.contains(sync.as_str()) {
+          return Err(format!("sync terminal '{}' is undefined", sync));
+        }
+      }
+    }
+
+    let mut leveled = HashSet::new();
+    for level in &self.prec {
+      for token in &level.tokens {
+        if !token_names.contains(token.as_str()) {
+          return Err(format!("precedence token '{}' is undefined", token));
+        }
+        if !leveled.insert(token.as_str()) {
+          return Err(format!("token '{}' assigned conflicting precedence levels",
+            token));
+        }
+      }
+    }
+
     Ok(())
   }
 }
 
+#[cfg(feature = "serde")]
+impl Grammar {
+  /// Serializes a validated grammar to `writer` as JSON so a program can cache
+  /// it and reload with [`from_reader`](Self::from_reader) instead of rebuilding
+  /// it through the combinators at every start. Rule order is preserved across
+  /// the round-trip. Building a parser from a reloaded grammar still runs
+  /// [`validate`](Self::validate).
+  pub fn to_writer<W: std::io::Write>(&self, writer: W) -> Result<(), String> {
+    serde_json::to_writer(writer, self).map_err(|e| e.to_string())
+  }
+
+  /// Reloads a grammar written by [`to_writer`](Self::to_writer).
+  pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Grammar, String> {
+    serde_json::from_reader(reader).map_err(|e| e.to_string())
+  }
+}
+
 impl RuleInner {
   fn validate(&self, names: &HashSet<&str>) -> Result<(), String> {
     match self {
@@ -234,6 +451,20 @@ impl RuleInner {
       Self::Prec(rule) => {
         rule.rule.validate(names)
       }
+      Self::PrecTok(rule) => {
+        if !names.contains(rule.token.as_str()) {
+          return Err(format!("precedence token '{}' is undefined", rule.token));
+        }
+        rule.rule.validate(names)
+      }
+      // unexpanded template applications only carry their argument rules; the
+      // expansion pass resolves the template name and splices concrete rules in
+      Self::Apply(rule) => {
+        for arg in &rule.args {
+          arg.validate(names)?;
+        }
+        Ok(())
+      }
     }
   }
 }
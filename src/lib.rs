@@ -1,11 +1,15 @@
-#![feature(box_syntax, box_patterns, bindings_after_at)]
-
 pub mod grammar;
 pub mod parser;
+pub mod scanner;
+pub mod ebnf;
 mod bnf;
+pub mod normalize;
+mod expand;
 
 pub use parser::Parser;
 pub use grammar::Grammar;
+pub use ebnf::parse_grammar;
+pub use bnf::{TermId, NontermId};
 
 pub fn build(grammar: Grammar) -> Result<Parser, String> {
   Parser::new(grammar)
@@ -0,0 +1,278 @@
+//! Desugaring pass that lowers the EBNF sugar in a [`Grammar`] into a core
+//! grammar whose rules only ever use [`RuleInner::Sym`], [`RuleInner::Seq`], and
+//! [`RuleInner::Or`], plus any [`RuleInner::Prec`]/[`RuleInner::PrecTok`]
+//! precedence annotations, which are kept around their desugared bodies.
+//!
+//! Each `Many`/`Some`/`Option`/`SepBy`/`SepBy1` operator is replaced by a fresh
+//! synthetic nonterminal spliced into `rules`, following the textbook
+//! expansions:
+//!
+//! * `r*`            ->  `R' -> ε | R' r`
+//! * `r+`            ->  `r R'`, with `R'` the `r*`
+//! * `r?`            ->  `ε | r`
+//! * `sepBy1(s, r)`  ->  `r (s r)*`
+//! * `sepBy(s, r)`   ->  `ε | sepBy1(s, r)`
+//!
+//! nested `Seq`/`Or` are flattened in passing. Fresh names are generated
+//! deterministically (`__rep_0`, `__opt_1`, …) and kept distinct from every
+//! token and rule name already present. Alongside the rewritten grammar the
+//! pass returns a mapping from each synthetic nonterminal back to the EBNF
+//! construct it replaced, so a parse tree over the core grammar can be
+//! re-sugared into the shape of the original.
+
+use std::collections::HashSet;
+use indexmap::IndexMap;
+use crate::grammar::*;
+
+/// The result of [`normalize`]: a core grammar plus the synthetic-nonterminal
+/// bookkeeping needed to re-sugar parse trees.
+pub struct Normalized {
+  pub grammar: Grammar,
+  /// synthetic nonterminal name -> the EBNF construct it was lowered from,
+  /// in generation order
+  pub desugared: IndexMap<String, RuleInner>,
+}
+
+/// Lowers every EBNF operator in `grammar` into core `Sym`/`Seq`/`Or` rules;
+/// `Prec`/`PrecTok` annotations are kept.
+pub fn normalize(grammar: &Grammar) -> Normalized {
+  let mut reserved = grammar.tokens.iter().cloned().collect::<HashSet<_>>();
+  reserved.extend(grammar.rules.keys().cloned());
+
+  let mut norm = Normalizer {
+    reserved,
+    extra: IndexMap::new(),
+    desugared: IndexMap::new(),
+    counter: 0,
+  };
+
+  let mut rules = IndexMap::new();
+  for (name, rule) in &grammar.rules {
+    rules.insert(name.clone(), Rule(norm.core(rule.0.clone())));
+  }
+  rules.extend(norm.extra.into_iter().map(|(n, r)| (n, Rule(r))));
+
+  let grammar = Grammar {
+    tokens: grammar.tokens.clone(),
+    start: grammar.start.clone(),
+    rules,
+    recovery: grammar.recovery.clone(),
+    prec: grammar.prec.clone(),
+    templates: grammar.templates.clone(),
+  };
+
+  Normalized {
+    grammar,
+    desugared: norm.desugared,
+  }
+}
+
+struct Normalizer {
+  reserved: HashSet<String>,
+  /// synthetic rules, appended to the grammar after the originals
+  extra: IndexMap<String, RuleInner>,
+  desugared: IndexMap<String, RuleInner>,
+  counter: usize,
+}
+
+impl Normalizer {
+  /// Rewrites a rule into an equivalent core rule, recursing into children and
+  /// hoisting EBNF operators into fresh synthetic nonterminals.
+  fn core(&mut self, rule: RuleInner) -> RuleInner {
+    match rule {
+      RuleInner::Sym(s) => RuleInner::Sym(s),
+      RuleInner::Seq(rules) => {
+        let mut flat = vec![];
+        for r in rules {
+          match self.core(r) {
+            RuleInner::Seq(inner) => flat.extend(inner),
+            other => flat.push(other),
+          }
+        }
+        RuleInner::Seq(flat)
+      }
+      RuleInner::Or(rules) => {
+        let mut flat = vec![];
+        for r in rules {
+          match self.core(r) {
+            RuleInner::Or(inner) => flat.extend(inner),
+            other => flat.push(other),
+          }
+        }
+        RuleInner::Or(flat)
+      }
+      RuleInner::Prec(p) => {
+        // precedence annotations are carried through verbatim; only their body
+        // is desugared
+        let RulePrec { prec, assoc, rule } = *p;
+        RuleInner::Prec(Box::new(RulePrec { prec, assoc, rule: self.core(rule) }))
+      }
+      RuleInner::PrecTok(pt) => {
+        let RulePrecTok { token, rule } = *pt;
+        RuleInner::PrecTok(Box::new(RulePrecTok { token, rule: self.core(rule) }))
+      }
+      RuleInner::Apply(_) => {
+        unreachable!("template applications are expanded before normalization")
+      }
+      rule @ (RuleInner::Many(_) | RuleInner::Some(_) | RuleInner::Option(_)
+        | RuleInner::SepBy(_) | RuleInner::SepBy1(_)) => {
+        self.desugar(rule)
+      }
+    }
+  }
+
+  /// Lowers a single EBNF operator, returning the core rule that replaces it.
+  fn desugar(&mut self, rule: RuleInner) -> RuleInner {
+    match rule.clone() {
+      RuleInner::Many(rep) => {
+        let RuleRep { rule: inner } = *rep;
+        let elem = self.as_sym(inner);
+        self.rep(elem, rule, "__rep")
+      }
+      RuleInner::Some(rep) => {
+        // r R', with R' the r*
+        let RuleRep { rule: inner } = *rep;
+        let elem = self.as_sym(inner);
+        let rep = self.rep(elem.clone(), RuleInner::Many(Box::new(RuleRep {
+          rule: elem.clone(),
+        })), "__rep");
+        RuleInner::Seq(vec![elem, rep])
+      }
+      RuleInner::Option(rep) => {
+        let RuleRep { rule: inner } = *rep;
+        let elem = self.as_sym(inner);
+        let name = self.fresh("__opt");
+        self.desugared.insert(name.clone(), rule);
+        self.extra.insert(name.clone(), RuleInner::Or(vec![
+          RuleInner::Seq(vec![]),
+          elem,
+        ]));
+        RuleInner::Sym(name)
+      }
+      RuleInner::SepBy1(sb) => {
+        let RuleSepBy { sep, rule: inner } = *sb;
+        self.sep_by1(sep, inner, rule)
+      }
+      RuleInner::SepBy(sb) => {
+        let RuleSepBy { sep, rule: inner } = *sb;
+        // ε | sepBy1(sep, rule)
+        let origin = RuleInner::SepBy1(Box::new(RuleSepBy {
+          sep: sep.clone(),
+          rule: inner.clone(),
+        }));
+        let sep_by1 = self.sep_by1(sep, inner, origin);
+        let name = self.fresh("__sep");
+        self.desugared.insert(name.clone(), rule);
+        self.extra.insert(name.clone(), RuleInner::Or(vec![
+          RuleInner::Seq(vec![]),
+          sep_by1,
+        ]));
+        RuleInner::Sym(name)
+      }
+      _ => unreachable!("desugar called on a core rule"),
+    }
+  }
+
+  /// `sepBy1(sep, rule)` as `rule (sep rule)*`, returning the core rule.
+  fn sep_by1(&mut self, sep: RuleInner, inner: RuleInner, origin: RuleInner) -> RuleInner {
+    let elem = self.as_sym(inner);
+    let sep = self.as_sym(sep);
+    let group = self.as_sym(RuleInner::Seq(vec![sep, elem.clone()]));
+    let rep = self.rep(group.clone(), RuleInner::Many(Box::new(RuleRep {
+      rule: group,
+    })), "__rep");
+    let name = self.fresh("__sep");
+    self.desugared.insert(name.clone(), origin);
+    self.extra.insert(name.clone(), RuleInner::Seq(vec![elem, rep]));
+    RuleInner::Sym(name)
+  }
+
+  /// Emits `N -> ε | N elem` and returns `Sym(N)`, recording `origin` as what
+  /// the fresh nonterminal `N` stands for.
+  fn rep(&mut self, elem: RuleInner, origin: RuleInner, prefix: &str) -> RuleInner {
+    let name = self.fresh(prefix);
+    self.desugared.insert(name.clone(), origin);
+    self.extra.insert(name.clone(), RuleInner::Or(vec![
+      RuleInner::Seq(vec![]),
+      RuleInner::Seq(vec![RuleInner::Sym(name.clone()), elem]),
+    ]));
+    RuleInner::Sym(name)
+  }
+
+  /// Reduces a rule to a single symbol, hoisting it into a fresh nonterminal
+  /// unless it already is one.
+  fn as_sym(&mut self, rule: RuleInner) -> RuleInner {
+    match self.core(rule) {
+      RuleInner::Sym(s) => RuleInner::Sym(s),
+      other => {
+        let name = self.fresh("__grp");
+        self.desugared.insert(name.clone(), other.clone());
+        self.extra.insert(name.clone(), other);
+        RuleInner::Sym(name)
+      }
+    }
+  }
+
+  /// A fresh `{prefix}_{n}` name guaranteed not to collide with any existing or
+  /// previously generated name.
+  fn fresh(&mut self, prefix: &str) -> String {
+    loop {
+      let name = format!("{}_{}", prefix, self.counter);
+      self.counter += 1;
+      if self.reserved.insert(name.clone()) {
+        return name;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::*;
+
+  /// Asserts that every rule of a normalized grammar is built only from `Sym`,
+  /// `Seq`, `Or`, and `Prec`.
+  fn assert_core(rule: &RuleInner) {
+    match rule {
+      RuleInner::Sym(_) => {}
+      RuleInner::Seq(rules) | RuleInner::Or(rules) => {
+        rules.iter().for_each(assert_core);
+      }
+      RuleInner::Prec(p) => assert_core(&p.rule),
+      other => panic!("sugar leaked into core grammar: {:?}", other),
+    }
+  }
+
+  #[test]
+  fn lowers_every_operator() {
+    let gram = grammar(
+      &["a", "b", ",", ";"],
+      &["S"],
+      &[
+        (
+          "S",
+          seq([
+            many(sym("a")),
+            some(sym("b")),
+            option(sym("a")),
+            sep_by(sym(","), sym("a")),
+            sep_by1(sym(";"), sym("b")),
+          ]),
+        ),
+      ]).unwrap();
+    gram.validate().unwrap();
+
+    let norm = normalize(&gram);
+
+    for (_, rule) in &norm.grammar.rules {
+      assert_core(&rule.0);
+    }
+    // every operator spawned at least one synthetic nonterminal, and each maps
+    // back to the construct it replaced
+    assert!(!norm.desugared.is_empty());
+    for name in norm.desugared.keys() {
+      assert!(norm.grammar.rules.contains_key(name));
+    }
+  }
+}
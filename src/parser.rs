@@ -1,20 +1,65 @@
+use std::ops::Range;
 use crate::grammar::Grammar;
 use crate::bnf::*;
 
 mod state;
 mod bitset;
 mod sets;
+mod tree;
+mod table;
+
+pub use tree::Cst;
+pub use table::{Action, Diagnostic, ParseResult, ParseTable};
 
 pub struct Parser {
+  table: ParseTable,
 }
 
 impl Parser {
   pub(crate) fn new(grammar: Grammar) -> Result<Self, String> {
+    let grammar = grammar.expand()?;
     grammar.validate()?;
     let mut bnf: Bnf = grammar.into();
     bnf.augment();
-    self::state::gen_states(&bnf);
+    let states = self::state::gen_states(&bnf);
+    let table = ParseTable::build(&bnf, &states);
+
+    Ok(Parser { table })
+  }
+
+  /// Parses a stream of `(TermId, span)` tokens into a typed [`Cst`].
+  pub fn parse<I>(&self, tokens: I) -> Result<Cst, String>
+  where
+    I: IntoIterator<Item = (TermId, Range<usize>)>,
+  {
+    self.table.parse(tokens)
+  }
+
+  /// Parses with panic-mode error recovery, returning a best-effort partial
+  /// tree plus every diagnostic collected in one pass. The grammar must declare
+  /// an `error` terminal via [`Grammar::recover`] for recovery to kick in.
+  pub fn parse_recover<I>(&self, tokens: I) -> ParseResult
+  where
+    I: IntoIterator<Item = (TermId, Range<usize>)>,
+  {
+    self.table.parse_recover(tokens)
+  }
+
+  /// The underlying parse table, for embedding a prebuilt parser.
+  pub fn table(&self) -> &ParseTable {
+    &self.table
+  }
 
-    Ok(Parser {})
+  /// Serializes the parser's table to bytes so a consumer can reload it with
+  /// [`from_bytes`](Self::from_bytes) and skip LALR generation.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    self.table.to_bytes()
   }
-}
\ No newline at end of file
+
+  /// Reconstructs a runnable parser from [`to_bytes`](Self::to_bytes) output.
+  pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+    Ok(Parser {
+      table: ParseTable::from_bytes(bytes)?,
+    })
+  }
+}
@@ -34,6 +34,29 @@ impl BitSet {
       1 << (bit as u64 % BLOCK_NBITS as u64);
   }
 
+  /// Returns whether the bit is in the set.
+  pub fn contains(&self, bit: usize) -> bool {
+    self.slice[bit / BLOCK_NBITS]
+      & (1 << (bit as u64 % BLOCK_NBITS as u64)) != 0
+  }
+
+  /// Removes a bit, returning whether the set changed.
+  pub fn remove(&mut self, bit: usize) -> bool {
+    let block = &mut self.slice[bit / BLOCK_NBITS];
+    let old = *block;
+    *block &= !(1 << (bit as u64 % BLOCK_NBITS as u64));
+    old != *block
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.slice.iter().all(|&b| b == 0)
+  }
+
+  /// The number of bits in the set, summed across blocks.
+  pub fn len(&self) -> usize {
+    self.slice.iter().map(|b| b.count_ones() as usize).sum()
+  }
+
   /// Returns whether the set has changed.
   pub fn union_with(&mut self, other: &BitSet) -> bool {
     let mut changed = false;
@@ -45,6 +68,65 @@ impl BitSet {
     changed
   }
 
+  /// Intersects with `other` in place, returning whether the set changed.
+  pub fn intersect_with(&mut self, other: &BitSet) -> bool {
+    let mut changed = false;
+    for i in 0..self.slice.len() {
+      let old = self.slice[i];
+      self.slice[i] &= other.slice[i];
+      changed |= old != self.slice[i];
+    }
+    changed
+  }
+
+  /// Removes every bit in `other` from `self`, returning whether the set
+  /// changed.
+  pub fn difference_with(&mut self, other: &BitSet) -> bool {
+    let mut changed = false;
+    for i in 0..self.slice.len() {
+      let old = self.slice[i];
+      self.slice[i] &= !other.slice[i];
+      changed |= old != self.slice[i];
+    }
+    changed
+  }
+
+  /// Returns whether the two sets share no bit.
+  pub fn is_disjoint(&self, other: &BitSet) -> bool {
+    self.slice.iter().zip(other.slice.iter())
+      .all(|(a, b)| a & b == 0)
+  }
+
+  /// Returns whether every bit of `self` is also in `other`.
+  pub fn is_subset(&self, other: &BitSet) -> bool {
+    self.slice.iter().zip(other.slice.iter())
+      .all(|(a, b)| a & !b == 0)
+  }
+
+  /// Complements the set over `0..num_bits` in place, returning whether the
+  /// set changed. The unused high bits of the final block are masked off so a
+  /// flipped bit past `num_bits` never leaks into [`iter`](Self::iter).
+  pub fn complement(&mut self, num_bits: usize) -> bool {
+    let mut changed = false;
+    for i in 0..self.slice.len() {
+      let old = self.slice[i];
+      self.slice[i] = !self.slice[i];
+      changed |= old != self.slice[i];
+    }
+
+    // clear the bits past `num_bits` in the last block
+    let rem = num_bits % BLOCK_NBITS;
+    if rem != 0 {
+      if let Some(last) = self.slice.last_mut() {
+        let mask = (1 << rem as u64) - 1;
+        let old = *last;
+        *last &= mask;
+        changed |= old != *last;
+      }
+    }
+    changed
+  }
+
   pub fn iter(&self) -> Iter {
     Iter {
       slice: &*self.slice,
@@ -54,7 +136,7 @@ impl BitSet {
   }
 
   pub fn get(&self, bit: usize) -> bool {
-    self.slice[bit / BLOCK_NBITS] & (bit as u64 % BLOCK_NBITS as u64) != 0
+    self.slice[bit / BLOCK_NBITS] & (1 << (bit as u64 % BLOCK_NBITS as u64)) != 0
   }
 }
 
@@ -109,4 +191,66 @@ mod tests {
 
     assert_eq!(vec, vec![3, 7, 14]);
   }
+
+  #[test]
+  fn membership_and_len() {
+    let mut set = BitSet::new(15);
+    assert!(set.is_empty());
+
+    set.insert(3);
+    set.insert(7);
+    assert!(set.contains(3));
+    assert!(!set.contains(4));
+    assert_eq!(set.len(), 2);
+
+    assert!(set.remove(3));
+    assert!(!set.remove(3));
+    assert!(!set.contains(3));
+    assert_eq!(set.len(), 1);
+  }
+
+  #[test]
+  fn set_algebra() {
+    let mut a = BitSet::new(15);
+    a.insert(1);
+    a.insert(3);
+    a.insert(5);
+
+    let mut b = BitSet::new(15);
+    b.insert(3);
+    b.insert(5);
+    b.insert(9);
+
+    assert!(!a.is_subset(&b));
+    assert!(b.is_subset(&{ let mut u = a.clone(); u.union_with(&b); u }));
+
+    let mut inter = a.clone();
+    assert!(inter.intersect_with(&b));
+    assert_eq!(inter.iter().collect::<Vec<_>>(), vec![3, 5]);
+
+    let mut diff = a.clone();
+    assert!(diff.difference_with(&b));
+    assert_eq!(diff.iter().collect::<Vec<_>>(), vec![1]);
+
+    let mut disjoint = BitSet::new(15);
+    disjoint.insert(2);
+    assert!(a.is_disjoint(&disjoint));
+    assert!(!a.is_disjoint(&b));
+  }
+
+  #[test]
+  fn complement_masks_unused_high_bits() {
+    // 10 bits land in a single 64-bit block; complement must not expose the
+    // 54 padding bits past bit 9.
+    let mut set = BitSet::new(10);
+    set.insert(2);
+    set.insert(8);
+
+    assert!(set.complement(10));
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 1, 3, 4, 5, 6, 7, 9]);
+
+    // complementing the full range twice is the identity.
+    set.complement(10);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![2, 8]);
+  }
 }
\ No newline at end of file
@@ -0,0 +1,759 @@
+//! The compact, self-contained parse-table artifact and the table-driven
+//! runtime driver.
+//!
+//! The table is the "program" of a small bytecode machine: a dense `ACTION`
+//! array keyed by `(state, TermId)` and a dense `GOTO` array keyed by
+//! `(state, NontermId)`, plus just enough per-production metadata to fold the
+//! tree. Everything needed to parse lives in the table, so a consumer can embed
+//! a prebuilt parser via [`ParseTable::to_bytes`] / [`ParseTable::from_bytes`]
+//! and skip LALR generation entirely at load time.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::bnf::*;
+use super::state::States;
+use super::tree::Cst;
+use super::tree::TreeBuilder;
+
+/// One `ACTION` cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+  Error,
+  Shift(u32),
+  Reduce(u32),
+  Accept,
+}
+
+/// The per-production metadata the driver needs: where a reduce goes, how many
+/// stack entries it pops, which tree action it performs, and its `prec()`
+/// precedence and associativity. `prec` and `assoc` are carried through the
+/// byte format only; [`resolve`] does not read them.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ProdInfo {
+  pub(crate) nonterm_id: u32,
+  pub(crate) num_symbols: u32,
+  pub(crate) action: ProdAction,
+  pub(crate) prec: Option<u16>,
+  pub(crate) assoc: Assoc,
+}
+
+/// A serializable LALR parse table.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseTable {
+  num_states: u32,
+  /// real terminals plus one end-of-input column
+  num_terms: u32,
+  num_nonterms: u32,
+  /// `num_states * num_terms`, row-major
+  action: Vec<Action>,
+  /// `num_states * num_nonterms`, row-major; `-1` means no goto
+  goto: Vec<i64>,
+  prods: Vec<ProdInfo>,
+  /// `(nonterm, start state)` for each start symbol, in declaration order
+  starts: Vec<(u32, u32)>,
+  /// the `error` pseudo-terminal column used by panic-mode recovery, if the
+  /// grammar declared one
+  error: Option<u32>,
+  /// the synchronizing terminals recovery discards input up to
+  sync: Vec<u32>,
+}
+
+/// A recoverable syntax error reported by [`ParseTable::parse_recover`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+  /// the span of the offending token (an empty span at end-of-input)
+  pub span: Range<usize>,
+  pub message: String,
+}
+
+/// The outcome of an error-recovering parse: a best-effort partial tree plus
+/// every diagnostic collected in the single pass.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseResult {
+  pub tree: Option<Cst>,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseTable {
+  /// The end-of-input terminal column.
+  fn eof(&self) -> u32 {
+    self.num_terms - 1
+  }
+
+  fn action_at(&self, state: u32, term: u32) -> Action {
+    self.action[state as usize * self.num_terms as usize + term as usize]
+  }
+
+  fn goto_at(&self, state: u32, nonterm: u32) -> i64 {
+    self.goto[state as usize * self.num_nonterms as usize + nonterm as usize]
+  }
+
+  /// Builds a table from a generated LALR automaton.
+  pub(crate) fn build(bnf: &Bnf, states: &States) -> ParseTable {
+    let num_tokens = bnf.tokens.len();
+    let eof = num_tokens as u32;
+    let num_terms = num_tokens as u32 + 1;
+    let num_nonterms = bnf.nonterms.len() as u32;
+    let num_states = states.states.len() as u32;
+
+    let start_prods = bnf.starts.values()
+      .map(|nt| bnf.nonterms[nt.0 as usize].prod_range.start as u32)
+      .collect::<HashSet<_>>();
+
+    let prods = bnf.prods.iter()
+      .map(|p| ProdInfo {
+        nonterm_id: p.nonterm_id.0,
+        num_symbols: p.symbols.len() as u32,
+        action: p.action,
+        prec: p.prec,
+        assoc: p.assoc,
+      })
+      .collect::<Vec<_>>();
+
+    // grammar-level precedence of each terminal column and each production,
+    // used to resolve shift/reduce conflicts below
+    let term_prec = (0..num_terms)
+      .map(|t| bnf.token_prec.get(&TermId(t)).copied())
+      .collect::<Vec<_>>();
+    let prod_prec = bnf.prods.iter()
+      .map(|p| prod_precedence(p, &bnf.token_prec))
+      .collect::<Vec<_>>();
+
+    let mut action = vec![Action::Error; num_states as usize * num_terms as usize];
+    let mut goto = vec![-1i64; num_states as usize * num_nonterms as usize];
+
+    for (s, (_, state)) in states.states.iter().enumerate() {
+      let s = s as u32;
+
+      for (sym, &target) in &state.transitions {
+        match sym {
+          Symbol::Term(t) => {
+            action[s as usize * num_terms as usize + t.0 as usize] = Action::Shift(target);
+          }
+          Symbol::Nonterm(nt) => {
+            goto[s as usize * num_nonterms as usize + nt.0 as usize] = target as i64;
+          }
+        }
+      }
+
+      for item in &state.items {
+        let prod = &bnf.prods[item.prod_ix as usize];
+        if (item.dot as usize) < prod.symbols.len() {
+          continue;
+        }
+        if start_prods.contains(&item.prod_ix) {
+          action[s as usize * num_terms as usize + eof as usize] = Action::Accept;
+          continue;
+        }
+        for la in item.lookaheads.iter() {
+          let cell = &mut action[s as usize * num_terms as usize + la];
+          *cell = resolve(*cell, item.prod_ix, term_prec[la], prod_prec[item.prod_ix as usize], &prods);
+        }
+      }
+    }
+
+    let starts = bnf.starts.values()
+      .map(|nt| (nt.0, states.starts[nt]))
+      .collect();
+
+    let (error, sync) = match &bnf.recovery {
+      Some(r) => (Some(r.error.0), r.sync.iter().map(|t| t.0).collect()),
+      None => (None, vec![]),
+    };
+
+    ParseTable {
+      num_states,
+      num_terms,
+      num_nonterms,
+      action,
+      goto,
+      prods,
+      starts,
+      error,
+      sync,
+    }
+  }
+
+  /// Runs the shift/reduce loop over `tokens`, returning the CST of the first
+  /// start symbol.
+  pub fn parse<I>(&self, tokens: I) -> Result<Cst, String>
+  where
+    I: IntoIterator<Item = (TermId, Range<usize>)>,
+  {
+    let start_state = self.starts.first()
+      .map(|&(_, s)| s)
+      .ok_or_else(|| format!("grammar has no start symbol"))?;
+
+    let mut stack = vec![start_state];
+    let mut builder = TreeBuilder::new();
+    let mut input = tokens.into_iter().peekable();
+
+    loop {
+      let state = *stack.last().unwrap();
+      let (term, span) = match input.peek() {
+        Some((t, span)) => (t.0, span.clone()),
+        None => (self.eof(), 0..0),
+      };
+
+      match self.action_at(state, term) {
+        Action::Shift(next) => {
+          builder.shift(TermId(term), span);
+          stack.push(next);
+          input.next();
+        }
+        Action::Reduce(prod_ix) => {
+          let info = self.prods[prod_ix as usize];
+          for _ in 0..info.num_symbols {
+            stack.pop();
+          }
+          let top = *stack.last().unwrap();
+          let goto = self.goto_at(top, info.nonterm_id);
+          if goto < 0 {
+            return Err(format!("no goto for nonterminal {} in state {}",
+              info.nonterm_id, top));
+          }
+          stack.push(goto as u32);
+          builder.reduce(info.action, info.num_symbols as usize, NontermId(info.nonterm_id));
+        }
+        Action::Accept => {
+          return Ok(builder.finish());
+        }
+        Action::Error => {
+          return Err(format!("unexpected token at byte {}", span.start));
+        }
+      }
+    }
+  }
+
+  /// Runs the shift/reduce loop with panic-mode error recovery, collecting a
+  /// [`Diagnostic`] per unexpected token and a best-effort partial tree instead
+  /// of aborting on the first error.
+  ///
+  /// On an unexpected token the driver pops states until one can shift the
+  /// `error` pseudo-terminal, shifts it, then discards input until the resulting
+  /// state can act on the lookahead or the lookahead is a declared
+  /// synchronizing terminal. A grammar without a
+  /// declared `error` terminal behaves like [`parse`](Self::parse): the first
+  /// error ends the parse with no tree.
+  pub fn parse_recover<I>(&self, tokens: I) -> ParseResult
+  where
+    I: IntoIterator<Item = (TermId, Range<usize>)>,
+  {
+    let start_state = match self.starts.first() {
+      Some(&(_, s)) => s,
+      None => return ParseResult {
+        tree: None,
+        diagnostics: vec![Diagnostic {
+          span: 0..0,
+          message: format!("grammar has no start symbol"),
+        }],
+      },
+    };
+
+    let mut stack = vec![start_state];
+    let mut builder = TreeBuilder::new();
+    let mut input = tokens.into_iter().peekable();
+    let mut diagnostics = vec![];
+
+    loop {
+      let state = *stack.last().unwrap();
+      let (term, span) = match input.peek() {
+        Some((t, span)) => (t.0, span.clone()),
+        None => (self.eof(), 0..0),
+      };
+
+      match self.action_at(state, term) {
+        Action::Shift(next) => {
+          builder.shift(TermId(term), span);
+          stack.push(next);
+          input.next();
+        }
+        Action::Reduce(prod_ix) => {
+          let info = self.prods[prod_ix as usize];
+          for _ in 0..info.num_symbols {
+            stack.pop();
+          }
+          let top = *stack.last().unwrap();
+          let goto = self.goto_at(top, info.nonterm_id);
+          if goto < 0 {
+            // no goto is only reachable mid-recovery; treat it as a dead end
+            return ParseResult { tree: None, diagnostics };
+          }
+          stack.push(goto as u32);
+          builder.reduce(info.action, info.num_symbols as usize, NontermId(info.nonterm_id));
+        }
+        Action::Accept => {
+          return ParseResult {
+            tree: Some(builder.finish()),
+            diagnostics,
+          };
+        }
+        Action::Error => {
+          diagnostics.push(Diagnostic {
+            span: span.clone(),
+            message: format!("unexpected token at byte {}", span.start),
+          });
+          if !self.recover(&mut stack, &mut builder, &mut input, span.start) {
+            return ParseResult { tree: None, diagnostics };
+          }
+        }
+      }
+    }
+  }
+
+  /// Performs one panic-mode recovery step, mutating the parse stacks in place.
+  /// Returns `false` when recovery is impossible (no `error` terminal, or no
+  /// open state can shift it), in which case the caller abandons the parse.
+  fn recover<I>(
+    &self,
+    stack: &mut Vec<u32>,
+    builder: &mut TreeBuilder,
+    input: &mut std::iter::Peekable<I>,
+    pos: usize,
+  ) -> bool
+  where
+    I: Iterator<Item = (TermId, Range<usize>)>,
+  {
+    let error = match self.error {
+      Some(e) => e,
+      None => return false,
+    };
+
+    // pop states until one can shift `error`; the builder value stack tracks
+    // the state stack one-to-one (minus the initial start state), so discard a
+    // value for every state popped
+    let target = loop {
+      let state = *stack.last().unwrap();
+      if let Action::Shift(next) = self.action_at(state, error) {
+        break next;
+      }
+      if stack.len() == 1 {
+        return false;
+      }
+      stack.pop();
+      builder.pop_discard();
+    };
+
+    builder.shift(TermId(error), pos..pos);
+    stack.push(target);
+
+    // discard input until the current state can act on the lookahead, stopping
+    // at any declared synchronizing terminal or end-of-input
+    loop {
+      let state = *stack.last().unwrap();
+      let term = match input.peek() {
+        Some((t, _)) => t.0,
+        None => return true,
+      };
+      if self.sync.contains(&term) || !matches!(self.action_at(state, term), Action::Error) {
+        return true;
+      }
+      input.next();
+    }
+  }
+
+  /// Serializes the table to a compact little-endian byte blob so a consumer can
+  /// embed a prebuilt parser. Round-trips through [`from_bytes`](Self::from_bytes).
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut w = Writer::new();
+    w.u32(self.num_states);
+    w.u32(self.num_terms);
+    w.u32(self.num_nonterms);
+
+    w.u32(self.action.len() as u32);
+    for a in &self.action {
+      w.action(*a);
+    }
+
+    w.u32(self.goto.len() as u32);
+    for g in &self.goto {
+      w.i64(*g);
+    }
+
+    w.u32(self.prods.len() as u32);
+    for p in &self.prods {
+      w.u32(p.nonterm_id);
+      w.u32(p.num_symbols);
+      w.u8(prod_action_tag(p.action));
+      match p.prec {
+        Some(prec) => { w.u8(1); w.u16(prec); }
+        None => { w.u8(0); w.u16(0); }
+      }
+      w.u8(assoc_tag(p.assoc));
+    }
+
+    w.u32(self.starts.len() as u32);
+    for &(nt, state) in &self.starts {
+      w.u32(nt);
+      w.u32(state);
+    }
+
+    match self.error {
+      Some(e) => { w.u8(1); w.u32(e); }
+      None => { w.u8(0); w.u32(0); }
+    }
+    w.u32(self.sync.len() as u32);
+    for &t in &self.sync {
+      w.u32(t);
+    }
+
+    w.into_bytes()
+  }
+
+  /// Reconstructs a runnable table from [`to_bytes`](Self::to_bytes) output.
+  pub fn from_bytes(bytes: &[u8]) -> Result<ParseTable, String> {
+    let mut r = Reader::new(bytes);
+    let num_states = r.u32()?;
+    let num_terms = r.u32()?;
+    let num_nonterms = r.u32()?;
+
+    let action_len = r.u32()? as usize;
+    let mut action = Vec::with_capacity(action_len);
+    for _ in 0..action_len {
+      action.push(r.action()?);
+    }
+
+    let goto_len = r.u32()? as usize;
+    let mut goto = Vec::with_capacity(goto_len);
+    for _ in 0..goto_len {
+      goto.push(r.i64()?);
+    }
+
+    let prods_len = r.u32()? as usize;
+    let mut prods = Vec::with_capacity(prods_len);
+    for _ in 0..prods_len {
+      let nonterm_id = r.u32()?;
+      let num_symbols = r.u32()?;
+      let action = prod_action_from_tag(r.u8()?)?;
+      let has_prec = r.u8()?;
+      let prec_val = r.u16()?;
+      let prec = if has_prec == 1 { Some(prec_val) } else { None };
+      let assoc = assoc_from_tag(r.u8()?)?;
+      prods.push(ProdInfo { nonterm_id, num_symbols, action, prec, assoc });
+    }
+
+    let starts_len = r.u32()? as usize;
+    let mut starts = Vec::with_capacity(starts_len);
+    for _ in 0..starts_len {
+      starts.push((r.u32()?, r.u32()?));
+    }
+
+    let has_error = r.u8()?;
+    let error_val = r.u32()?;
+    let error = if has_error == 1 { Some(error_val) } else { None };
+    let sync_len = r.u32()? as usize;
+    let mut sync = Vec::with_capacity(sync_len);
+    for _ in 0..sync_len {
+      sync.push(r.u32()?);
+    }
+
+    Ok(ParseTable {
+      num_states,
+      num_terms,
+      num_nonterms,
+      action,
+      goto,
+      prods,
+      starts,
+      error,
+      sync,
+    })
+  }
+}
+
+/// The grammar-level precedence of a production: its `%prec` override if set,
+/// otherwise the precedence of its rightmost terminal. `None` when neither
+/// carries a precedence level.
+fn prod_precedence(
+  prod: &Production,
+  token_prec: &HashMap<TermId, (u16, Assoc)>,
+) -> Option<(u16, Assoc)> {
+  if let Some(term) = prod.prec_override {
+    return token_prec.get(&term).copied();
+  }
+  prod.symbols.iter().rev()
+    .find_map(|sym| match sym {
+      Symbol::Term(t) => Some(*t),
+      _ => None,
+    })
+    .and_then(|t| token_prec.get(&t).copied())
+}
+
+/// Resolves a shift/reduce or reduce/reduce conflict when installing a reduce.
+///
+/// A shift/reduce conflict is resolved with the grammar-level precedence table
+/// when both the lookahead token and the production carry a precedence: the
+/// higher precedence wins, and on a tie the shared associativity decides (left
+/// reduces, right shifts, none is an error). Absent precedence it defaults to
+/// shift, the conventional yacc resolution. Per-production `prec()` annotations
+/// are not consulted. Reduce/reduce conflicts pick the earlier-declared
+/// production.
+fn resolve(
+  existing: Action,
+  prod_ix: u32,
+  la_prec: Option<(u16, Assoc)>,
+  prod_prec: Option<(u16, Assoc)>,
+  prods: &[ProdInfo],
+) -> Action {
+  match existing {
+    // Accept is the augmented start's acceptance on eof; a completing reduce
+    // reachable in the same state (e.g. a nullable nonterminal carrying eof in
+    // its lookahead) must never overwrite it, or the parse loses its only
+    // termination cell.
+    Action::Accept => Action::Accept,
+    Action::Error => Action::Reduce(prod_ix),
+    Action::Shift(shift) => {
+      match (la_prec, prod_prec) {
+        (Some((lp, _)), Some((rp, assoc))) => {
+          if rp > lp {
+            Action::Reduce(prod_ix)
+          } else if rp < lp {
+            Action::Shift(shift)
+          } else {
+            match assoc {
+              Assoc::Left => Action::Reduce(prod_ix),
+              Assoc::Right => Action::Shift(shift),
+              Assoc::None => Action::Error,
+            }
+          }
+        }
+        _ => Action::Shift(shift),
+      }
+    }
+    Action::Reduce(other) => {
+      let keep = resolve_reduce_reduce(prod_ix, other, prods);
+      Action::Reduce(keep)
+    }
+  }
+}
+
+fn resolve_reduce_reduce(a: u32, b: u32, _prods: &[ProdInfo]) -> u32 {
+  a.min(b)
+}
+
+fn prod_action_tag(action: ProdAction) -> u8 {
+  match action {
+    ProdAction::None => 0,
+    ProdAction::StartMany => 1,
+    ProdAction::ContinueMany => 2,
+    ProdAction::StartMany1 => 3,
+    ProdAction::ContinueMany1 => 4,
+    ProdAction::EmptyOption => 5,
+    ProdAction::NonemptyOption => 6,
+    ProdAction::EmptySepBy => 7,
+    ProdAction::NonemptySepBy => 8,
+    ProdAction::StartSepBy1 => 9,
+    ProdAction::ContinueSepBy1 => 10,
+  }
+}
+
+fn prod_action_from_tag(tag: u8) -> Result<ProdAction, String> {
+  Ok(match tag {
+    0 => ProdAction::None,
+    1 => ProdAction::StartMany,
+    2 => ProdAction::ContinueMany,
+    3 => ProdAction::StartMany1,
+    4 => ProdAction::ContinueMany1,
+    5 => ProdAction::EmptyOption,
+    6 => ProdAction::NonemptyOption,
+    7 => ProdAction::EmptySepBy,
+    8 => ProdAction::NonemptySepBy,
+    9 => ProdAction::StartSepBy1,
+    10 => ProdAction::ContinueSepBy1,
+    _ => return Err(format!("invalid ProdAction tag {}", tag)),
+  })
+}
+
+fn assoc_tag(assoc: Assoc) -> u8 {
+  match assoc {
+    Assoc::None => 0,
+    Assoc::Left => 1,
+    Assoc::Right => 2,
+  }
+}
+
+fn assoc_from_tag(tag: u8) -> Result<Assoc, String> {
+  Ok(match tag {
+    0 => Assoc::None,
+    1 => Assoc::Left,
+    2 => Assoc::Right,
+    _ => return Err(format!("invalid Assoc tag {}", tag)),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::*;
+  use pretty_assertions::assert_eq;
+
+  /// A parenthesized skeleton of a [`Cst`]: terminals as `tN` (their `TermId`),
+  /// user nodes as `(..)`, repetitions as `[..]`, options as `?x`/`?`.
+  fn render(cst: &Cst) -> String {
+    match cst {
+      Cst::Terminal(t, _) => format!("t{}", t.0),
+      Cst::Node { children, .. } => format!("({})", render_all(children)),
+      Cst::Many(items) => format!("[{}]", render_all(items)),
+      Cst::Opt(Some(c)) => format!("?{}", render(c)),
+      Cst::Opt(None) => "?".to_owned(),
+    }
+  }
+
+  fn render_all(children: &[Cst]) -> String {
+    children.iter().map(render).collect::<Vec<_>>().join(" ")
+  }
+
+  fn toks(ids: &[u32]) -> Vec<(TermId, Range<usize>)> {
+    ids.iter().enumerate().map(|(i, &t)| (TermId(t), i..i + 1)).collect()
+  }
+
+  /// An ambiguous expression grammar resolved by a grammar-level precedence
+  /// table: `n=0 +=1 *=2 (=3 )=4`, with `+` binding looser than `*` and both
+  /// left-associative.
+  fn expr_parser() -> crate::Parser {
+    let gram = grammar_with_prec(
+      &["n", "+", "*", "(", ")"],
+      &["E"],
+      &[(Assoc::Left, &["+"]), (Assoc::Left, &["*"])],
+      &[(
+        "E",
+        seq([sym("E"), sym("+"), sym("E")])
+          | seq([sym("E"), sym("*"), sym("E")])
+          | seq([sym("("), sym("E"), sym(")")])
+          | sym("n"),
+      )],
+    ).unwrap();
+    crate::build(gram).unwrap()
+  }
+
+  #[test]
+  fn precedence_nests_tighter_operator_first() {
+    // n + n * n  ==>  n + (n * n)
+    let tree = expr_parser().parse(toks(&[0, 1, 0, 2, 0])).unwrap();
+    assert_eq!(render(&tree), "((t0) t1 ((t0) t2 (t0)))");
+  }
+
+  #[test]
+  fn left_associativity_nests_left() {
+    // n + n + n  ==>  (n + n) + n
+    let tree = expr_parser().parse(toks(&[0, 1, 0, 1, 0])).unwrap();
+    assert_eq!(render(&tree), "(((t0) t1 (t0)) t1 (t0))");
+  }
+
+  #[test]
+  fn unexpected_token_errors() {
+    assert!(expr_parser().parse(toks(&[1])).is_err());
+  }
+
+  #[test]
+  fn table_round_trips_through_bytes() {
+    let parser = expr_parser();
+    let reloaded = crate::Parser::from_bytes(&parser.to_bytes()).unwrap();
+    let input = toks(&[0, 2, 0, 1, 0]);
+    assert_eq!(
+      reloaded.parse(input.clone()).unwrap(),
+      parser.parse(input).unwrap(),
+    );
+  }
+
+  #[test]
+  fn recovery_collects_every_diagnostic() {
+    // P -> S* ; S -> n ";" | error ";". `x` (=2) is never shiftable, so each of
+    // the two bad statements trips recovery and resynchronizes on `;`.
+    let gram = grammar(
+      &["n", ";", "x", "error"],
+      &["P"],
+      &[
+        ("P", many(sym("S"))),
+        ("S", seq([sym("n"), sym(";")]) | seq([sym("error"), sym(";")])),
+      ],
+    ).unwrap().recover("error", &[";"]);
+    let parser = crate::build(gram).unwrap();
+
+    // n ; x ; x ;  -> one good statement then two recovered errors
+    let result = parser.parse_recover(toks(&[0, 1, 2, 1, 2, 1]));
+    assert_eq!(result.diagnostics.len(), 2);
+    assert!(result.tree.is_some());
+  }
+}
+
+/// Minimal little-endian byte writer for the table blob.
+struct Writer {
+  buf: Vec<u8>,
+}
+
+impl Writer {
+  fn new() -> Self {
+    Writer { buf: vec![] }
+  }
+  fn u8(&mut self, v: u8) {
+    self.buf.push(v);
+  }
+  fn u16(&mut self, v: u16) {
+    self.buf.extend_from_slice(&v.to_le_bytes());
+  }
+  fn u32(&mut self, v: u32) {
+    self.buf.extend_from_slice(&v.to_le_bytes());
+  }
+  fn i64(&mut self, v: i64) {
+    self.buf.extend_from_slice(&v.to_le_bytes());
+  }
+  fn action(&mut self, a: Action) {
+    match a {
+      Action::Error => self.u8(0),
+      Action::Shift(s) => { self.u8(1); self.u32(s); }
+      Action::Reduce(p) => { self.u8(2); self.u32(p); }
+      Action::Accept => self.u8(3),
+    }
+  }
+  fn into_bytes(self) -> Vec<u8> {
+    self.buf
+  }
+}
+
+/// Matching little-endian byte reader.
+struct Reader<'a> {
+  buf: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  fn new(buf: &'a [u8]) -> Self {
+    Reader { buf, pos: 0 }
+  }
+  fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+    if self.pos + n > self.buf.len() {
+      return Err(format!("unexpected end of table bytes"));
+    }
+    let slice = &self.buf[self.pos..self.pos + n];
+    self.pos += n;
+    Ok(slice)
+  }
+  fn u8(&mut self) -> Result<u8, String> {
+    Ok(self.take(1)?[0])
+  }
+  fn u16(&mut self) -> Result<u16, String> {
+    let b = self.take(2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+  }
+  fn u32(&mut self) -> Result<u32, String> {
+    let b = self.take(4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+  }
+  fn i64(&mut self) -> Result<i64, String> {
+    let b = self.take(8)?;
+    Ok(i64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+  }
+  fn action(&mut self) -> Result<Action, String> {
+    Ok(match self.u8()? {
+      0 => Action::Error,
+      1 => Action::Shift(self.u32()?),
+      2 => Action::Reduce(self.u32()?),
+      3 => Action::Accept,
+      tag => return Err(format!("invalid Action tag {}", tag)),
+    })
+  }
+}
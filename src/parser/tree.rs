@@ -0,0 +1,202 @@
+use std::ops::Range;
+use crate::bnf::*;
+
+/// A concrete syntax tree shaped like the user's original EBNF grammar rather
+/// than the augmented/normalized BNF.
+///
+/// The synthetic `rule*`/`rule+`/`rule?`/`sepBy` nonterminals the BNF lowering
+/// introduces never appear here: `StartMany`/`ContinueMany` productions collapse
+/// back into [`Cst::Many`], `EmptyOption`/`NonemptyOption` into [`Cst::Opt`], and
+/// the `SepBy` family into a flat [`Cst::Many`] with the separators dropped.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Cst {
+  /// A matched terminal and the span of source it covered.
+  Terminal(TermId, Range<usize>),
+  /// A user nonterminal and its children, in source order.
+  Node {
+    nonterm: NontermId,
+    children: Vec<Cst>,
+  },
+  /// The elements of a `rule*`, `rule+`, or `sepBy`/`sepBy1`.
+  Many(Vec<Cst>),
+  /// The body of a `rule?`.
+  Opt(Option<Box<Cst>>),
+}
+
+/// A value on the builder stack. Normal symbols carry a finished [`Cst`]; the
+/// synthetic repetition/option nonterminals carry the accumulator that their
+/// productions fold into, so it is never materialized as a real tree node.
+enum Value {
+  Tree(Cst),
+  Seq(Vec<Cst>),
+  Opt(Option<Cst>),
+}
+
+impl Value {
+  /// Coerces a value into the child node it contributes to its parent.
+  fn into_child(self) -> Cst {
+    match self {
+      Value::Tree(cst) => cst,
+      Value::Seq(items) => Cst::Many(items),
+      Value::Opt(opt) => Cst::Opt(opt.map(Box::new)),
+    }
+  }
+}
+
+/// Reconstructs a [`Cst`] from the stream of shift/reduce events the LR driver
+/// produces, mirroring how recursion-scheme-style AST layers separate the
+/// recursive shape from the node payload.
+///
+/// The driver pushes a terminal with [`shift`](TreeBuilder::shift) and folds a
+/// production with [`reduce`](TreeBuilder::reduce); [`finish`](TreeBuilder::finish)
+/// pops the single remaining value once the start symbol has been reduced.
+pub(crate) struct TreeBuilder {
+  stack: Vec<Value>,
+}
+
+impl TreeBuilder {
+  pub(crate) fn new() -> Self {
+    TreeBuilder { stack: vec![] }
+  }
+
+  pub(crate) fn shift(&mut self, term: TermId, span: Range<usize>) {
+    self.stack.push(Value::Tree(Cst::Terminal(term, span)));
+  }
+
+  /// Folds a production identified by its `action`, symbol count, and the
+  /// nonterminal it reduces to — the only metadata the serialized parse table
+  /// needs to carry.
+  pub(crate) fn reduce(&mut self, action: ProdAction, num_symbols: usize, nonterm: NontermId) {
+    let n = num_symbols;
+    match action {
+      ProdAction::StartMany => {
+        // rule* -> ε
+        self.stack.push(Value::Seq(vec![]));
+      }
+      ProdAction::ContinueMany | ProdAction::ContinueMany1 => {
+        // rule* -> rule* rule  /  rule+ -> rule+ rule
+        let child = self.pop().into_child();
+        let mut items = self.pop_seq();
+        items.push(child);
+        self.stack.push(Value::Seq(items));
+      }
+      ProdAction::StartMany1 => {
+        // rule+ -> rule
+        let child = self.pop().into_child();
+        self.stack.push(Value::Seq(vec![child]));
+      }
+      ProdAction::EmptyOption => {
+        // rule? -> ε
+        self.stack.push(Value::Opt(None));
+      }
+      ProdAction::NonemptyOption => {
+        // rule? -> rule. The option body is inlined rather than routed through a
+        // fresh nonterminal, so a `Seq` body (e.g. `option(seq([a, b]))`) folds
+        // several symbols here; gather all `num_symbols` of them.
+        let child = self.pop_wrapped(n);
+        self.stack.push(Value::Opt(Some(child)));
+      }
+      ProdAction::EmptySepBy => {
+        // sepBy(sep, rule) -> ε
+        self.stack.push(Value::Seq(vec![]));
+      }
+      ProdAction::NonemptySepBy => {
+        // sepBy(sep, rule) -> sepBy1(sep, rule): the accumulator passes through
+      }
+      ProdAction::StartSepBy1 => {
+        // sepBy1(sep, rule) -> rule
+        let child = self.pop().into_child();
+        self.stack.push(Value::Seq(vec![child]));
+      }
+      ProdAction::ContinueSepBy1 => {
+        // sepBy1(sep, rule) -> sepBy1(sep, rule) sep rule; drop the separator
+        let child = self.pop().into_child();
+        let _sep = self.pop();
+        let mut items = self.pop_seq();
+        items.push(child);
+        self.stack.push(Value::Seq(items));
+      }
+      ProdAction::None => {
+        let at = self.stack.len() - n;
+        let children = self.stack.split_off(at).into_iter()
+          .map(Value::into_child)
+          .collect();
+        self.stack.push(Value::Tree(Cst::Node {
+          nonterm,
+          children,
+        }));
+      }
+    }
+  }
+
+  /// Returns the tree for the user's start symbol. The driver accepts as soon
+  /// as it reaches the `$start -> S·` item, so the augmented start production is
+  /// never folded and the lone remaining value is `S`'s tree.
+  pub(crate) fn finish(mut self) -> Cst {
+    assert_eq!(self.stack.len(), 1, "unbalanced tree builder stack");
+    self.pop().into_child()
+  }
+
+  /// Drops the topmost value, used by panic-mode recovery when it pops a parse
+  /// state whose contribution is being discarded.
+  pub(crate) fn pop_discard(&mut self) {
+    self.pop();
+  }
+
+  fn pop(&mut self) -> Value {
+    self.stack.pop().expect("tree builder stack underflow")
+  }
+
+  /// Pops the top `n` values and folds them into a single child: the lone value
+  /// when `n == 1`, otherwise a [`Cst::Many`] preserving source order. Used by
+  /// actions whose body may have been inlined as a multi-symbol `Seq`.
+  fn pop_wrapped(&mut self, n: usize) -> Cst {
+    let at = self.stack.len() - n;
+    let mut children: Vec<Cst> = self.stack.split_off(at).into_iter()
+      .map(Value::into_child)
+      .collect();
+    if children.len() == 1 {
+      children.pop().unwrap()
+    } else {
+      Cst::Many(children)
+    }
+  }
+
+  fn pop_seq(&mut self) -> Vec<Cst> {
+    match self.pop() {
+      Value::Seq(items) => items,
+      _ => panic!("expected an accumulator on the tree builder stack"),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::grammar::*;
+  use pretty_assertions::assert_eq;
+
+  /// An `option(seq(..))` body inlines several symbols into one
+  /// `NonemptyOption` production, so the reduce must pop all of them or the
+  /// state and value stacks desync and `finish` panics.
+  #[test]
+  fn option_over_a_seq_body() {
+    let gram = grammar(
+      &["a", "b"],
+      &["S"],
+      &[("S", option(seq([sym("a"), sym("b")])))],
+    ).unwrap();
+    let parser = crate::build(gram).unwrap();
+
+    let present = parser
+      .parse(vec![(TermId(0), 0..1), (TermId(1), 1..2)])
+      .unwrap();
+    assert_eq!(present, Cst::Opt(Some(Box::new(Cst::Many(vec![
+      Cst::Terminal(TermId(0), 0..1),
+      Cst::Terminal(TermId(1), 1..2),
+    ])))));
+
+    let absent = parser.parse(std::iter::empty()).unwrap();
+    assert_eq!(absent, Cst::Opt(None));
+  }
+}
@@ -0,0 +1,543 @@
+//! Regex-based scanner generator.
+//!
+//! Each terminal carries a regular expression; the scanner compiles all of
+//! them into a single automaton and runs it with maximal munch to turn source
+//! text into a stream of `(TermId, span)` tokens that plug straight into the
+//! `tokens` index the rest of the pipeline already uses.
+//!
+//! The pipeline is the textbook one: every regex is compiled to an NFA
+//! fragment by Thompson construction, the fragments are joined under a shared
+//! start state, and the combined NFA is subset-constructed into a DFA table.
+
+use std::ops::Range;
+use crate::bnf::TermId;
+use crate::grammar::Grammar;
+
+/// An inclusive range of `char`s, the label on an NFA/DFA transition.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct CharRange {
+  lo: char,
+  hi: char,
+}
+
+impl CharRange {
+  fn contains(&self, c: char) -> bool {
+    self.lo <= c && c <= self.hi
+  }
+}
+
+/// An accept tag: the terminal a fragment recognizes and its declaration-order
+/// priority (lower wins on ties).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Tag {
+  term: TermId,
+  priority: u32,
+}
+
+struct NfaState {
+  /// labelled transitions
+  edges: Vec<(CharRange, usize)>,
+  /// epsilon transitions
+  eps: Vec<usize>,
+  accept: Option<Tag>,
+}
+
+struct Nfa {
+  states: Vec<NfaState>,
+}
+
+/// A half-built automaton with a single entry and a single exit state.
+struct Fragment {
+  start: usize,
+  end: usize,
+}
+
+impl Nfa {
+  fn new() -> Self {
+    Nfa { states: vec![] }
+  }
+
+  fn new_state(&mut self) -> usize {
+    let id = self.states.len();
+    self.states.push(NfaState {
+      edges: vec![],
+      eps: vec![],
+      accept: None,
+    });
+    id
+  }
+
+  fn edge(&mut self, from: usize, range: CharRange, to: usize) {
+    self.states[from].edges.push((range, to));
+  }
+
+  fn eps(&mut self, from: usize, to: usize) {
+    self.states[from].eps.push(to);
+  }
+}
+
+/// Compiles `src` into an NFA fragment appended to `nfa`.
+fn compile_regex(nfa: &mut Nfa, src: &str) -> Result<Fragment, String> {
+  let mut parser = RegexParser {
+    chars: src.chars().collect(),
+    pos: 0,
+    nfa,
+  };
+  let frag = parser.alternation()?;
+  if parser.pos != parser.chars.len() {
+    return Err(format!("unexpected '{}' in regex '{}'",
+      parser.chars[parser.pos], src));
+  }
+  Ok(frag)
+}
+
+struct RegexParser<'a> {
+  chars: Vec<char>,
+  pos: usize,
+  nfa: &'a mut Nfa,
+}
+
+impl<'a> RegexParser<'a> {
+  fn peek(&self) -> Option<char> {
+    self.chars.get(self.pos).copied()
+  }
+
+  fn bump(&mut self) -> Option<char> {
+    let c = self.peek();
+    if c.is_some() {
+      self.pos += 1;
+    }
+    c
+  }
+
+  fn alternation(&mut self) -> Result<Fragment, String> {
+    let mut branches = vec![self.concatenation()?];
+    while self.peek() == Some('|') {
+      self.bump();
+      branches.push(self.concatenation()?);
+    }
+    if branches.len() == 1 {
+      return Ok(branches.pop().unwrap());
+    }
+
+    // epsilon-forked start into every branch, joining on a shared end
+    let start = self.nfa.new_state();
+    let end = self.nfa.new_state();
+    for branch in branches {
+      self.nfa.eps(start, branch.start);
+      self.nfa.eps(branch.end, end);
+    }
+    Ok(Fragment { start, end })
+  }
+
+  fn concatenation(&mut self) -> Result<Fragment, String> {
+    let mut frag: Option<Fragment> = None;
+    while let Some(c) = self.peek() {
+      if c == '|' || c == ')' {
+        break;
+      }
+      let next = self.repetition()?;
+      frag = Some(match frag {
+        None => next,
+        Some(prev) => {
+          self.nfa.eps(prev.end, next.start);
+          Fragment { start: prev.start, end: next.end }
+        }
+      });
+    }
+    match frag {
+      Some(frag) => Ok(frag),
+      None => {
+        // empty alternative matches the empty string
+        let s = self.nfa.new_state();
+        Ok(Fragment { start: s, end: s })
+      }
+    }
+  }
+
+  fn repetition(&mut self) -> Result<Fragment, String> {
+    let atom = self.atom()?;
+    match self.peek() {
+      Some('*') => {
+        self.bump();
+        let start = self.nfa.new_state();
+        let end = self.nfa.new_state();
+        self.nfa.eps(start, atom.start);
+        self.nfa.eps(start, end);
+        self.nfa.eps(atom.end, atom.start);
+        self.nfa.eps(atom.end, end);
+        Ok(Fragment { start, end })
+      }
+      Some('+') => {
+        self.bump();
+        let end = self.nfa.new_state();
+        self.nfa.eps(atom.end, atom.start);
+        self.nfa.eps(atom.end, end);
+        Ok(Fragment { start: atom.start, end })
+      }
+      Some('?') => {
+        self.bump();
+        let start = self.nfa.new_state();
+        let end = self.nfa.new_state();
+        self.nfa.eps(start, atom.start);
+        self.nfa.eps(start, end);
+        self.nfa.eps(atom.end, end);
+        Ok(Fragment { start, end })
+      }
+      _ => Ok(atom),
+    }
+  }
+
+  fn atom(&mut self) -> Result<Fragment, String> {
+    match self.peek() {
+      Some('(') => {
+        self.bump();
+        let frag = self.alternation()?;
+        if self.bump() != Some(')') {
+          return Err(format!("unclosed '(' in regex"));
+        }
+        Ok(frag)
+      }
+      Some('[') => self.char_class(),
+      Some('.') => {
+        self.bump();
+        Ok(self.single(CharRange { lo: '\0', hi: char::MAX }))
+      }
+      Some(_) => {
+        let c = self.escaped_char()?;
+        Ok(self.single(CharRange { lo: c, hi: c }))
+      }
+      None => Err(format!("unexpected end of regex")),
+    }
+  }
+
+  fn char_class(&mut self) -> Result<Fragment, String> {
+    self.bump(); // '['
+    let negate = self.peek() == Some('^');
+    if negate {
+      self.bump();
+    }
+    let mut ranges = vec![];
+    while let Some(c) = self.peek() {
+      if c == ']' {
+        break;
+      }
+      let lo = self.escaped_char()?;
+      let hi = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+        self.bump();
+        self.escaped_char()?
+      } else {
+        lo
+      };
+      if hi < lo {
+        return Err(format!("inverted range '{}-{}' in char class", lo, hi));
+      }
+      ranges.push(CharRange { lo, hi });
+    }
+    if self.bump() != Some(']') {
+      return Err(format!("unclosed '[' in regex"));
+    }
+    if negate {
+      ranges = complement_ranges(&ranges);
+    }
+
+    let start = self.nfa.new_state();
+    let end = self.nfa.new_state();
+    for range in ranges {
+      self.nfa.edge(start, range, end);
+    }
+    Ok(Fragment { start, end })
+  }
+
+  /// Reads one possibly-backslash-escaped character.
+  fn escaped_char(&mut self) -> Result<char, String> {
+    match self.bump() {
+      Some('\\') => match self.bump() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some(c) => Ok(c),
+        None => Err(format!("trailing '\\' in regex")),
+      },
+      Some(c) => Ok(c),
+      None => Err(format!("unexpected end of regex")),
+    }
+  }
+
+  fn single(&mut self, range: CharRange) -> Fragment {
+    let start = self.nfa.new_state();
+    let end = self.nfa.new_state();
+    self.nfa.edge(start, range, end);
+    Fragment { start, end }
+  }
+}
+
+/// Complements a set of ranges over the whole `char` space.
+fn complement_ranges(ranges: &[CharRange]) -> Vec<CharRange> {
+  let mut sorted = ranges.to_vec();
+  sorted.sort_by_key(|r| r.lo);
+  let mut out = vec![];
+  let mut next = '\0' as u32;
+  for r in sorted {
+    let lo = r.lo as u32;
+    if lo > next {
+      out.push(CharRange {
+        lo: char::from_u32(next).unwrap(),
+        hi: char::from_u32(lo - 1).unwrap(),
+      });
+    }
+    next = next.max((r.hi as u32).saturating_add(1));
+  }
+  if next <= char::MAX as u32 {
+    if let Some(lo) = char::from_u32(next) {
+      out.push(CharRange { lo, hi: char::MAX });
+    }
+  }
+  out
+}
+
+struct DfaState {
+  /// labelled transitions, keyed by disjoint char ranges
+  trans: Vec<(CharRange, usize)>,
+  accept: Option<TermId>,
+}
+
+/// A compiled scanner: a DFA table plus the index of its start state.
+pub struct Scanner {
+  states: Vec<DfaState>,
+  start: usize,
+}
+
+impl Scanner {
+  /// Compiles one regex per terminal into a single DFA. `regexes` is in
+  /// declaration order; its index becomes the terminal's priority and its
+  /// `TermId`.
+  pub fn new(regexes: &[&str]) -> Result<Self, String> {
+    let mut nfa = Nfa::new();
+    let start = nfa.new_state();
+    for (i, re) in regexes.iter().enumerate() {
+      let frag = compile_regex(&mut nfa, re)?;
+      nfa.eps(start, frag.start);
+      nfa.states[frag.end].accept = Some(Tag {
+        term: TermId(i as u32),
+        priority: i as u32,
+      });
+    }
+    Ok(subset_construct(&nfa, start))
+  }
+
+  /// Runs maximal munch over `input`, emitting `(TermId, span)` for each token.
+  /// On the first character that fails to scan, returns `Err` with a message
+  /// naming the offending byte offset.
+  pub fn tokenize(&self, input: &str) -> Result<Vec<(TermId, Range<usize>)>, String> {
+    let mut tokens = vec![];
+    let mut offset = 0;
+    let bytes = input.as_bytes();
+
+    while offset < bytes.len() {
+      let mut state = self.start;
+      let mut last_accept: Option<(TermId, usize)> = None;
+      let mut cursor = offset;
+
+      for (i, c) in input[offset..].char_indices() {
+        match self.step(state, c) {
+          Some(next) => {
+            state = next;
+            cursor = offset + i + c.len_utf8();
+            if let Some(term) = self.states[state].accept {
+              last_accept = Some((term, cursor));
+            }
+          }
+          None => break,
+        }
+      }
+
+      match last_accept {
+        Some((term, end)) => {
+          tokens.push((term, offset..end));
+          offset = end;
+        }
+        None => return Err(format!("unexpected character at byte {}", offset)),
+      }
+    }
+
+    Ok(tokens)
+  }
+
+  fn step(&self, state: usize, c: char) -> Option<usize> {
+    self.states[state].trans.iter()
+      .find(|(range, _)| range.contains(c))
+      .map(|(_, target)| *target)
+  }
+}
+
+/// Subset-constructs a DFA from `nfa`, starting from the epsilon-closure of
+/// `start`.
+fn subset_construct(nfa: &Nfa, start: usize) -> Scanner {
+  use std::collections::HashMap;
+
+  let mut states: Vec<DfaState> = vec![];
+  let mut index: HashMap<Vec<usize>, usize> = HashMap::new();
+  let mut worklist: Vec<Vec<usize>> = vec![];
+
+  let start_set = epsilon_closure(nfa, &[start]);
+  index.insert(start_set.clone(), 0);
+  states.push(DfaState { trans: vec![], accept: accept_of(nfa, &start_set) });
+  worklist.push(start_set);
+
+  while let Some(set) = worklist.pop() {
+    let from = index[&set];
+
+    // split the outgoing char ranges of every member state into disjoint
+    // atomic intervals, then compute the move on each interval
+    let ranges = set.iter()
+      .flat_map(|&s| nfa.states[s].edges.iter().map(|(r, _)| *r))
+      .collect::<Vec<_>>();
+    for atom in atomic_ranges(&ranges) {
+      let mut targets = vec![];
+      for &s in &set {
+        for (range, to) in &nfa.states[s].edges {
+          if range.contains(atom.lo) {
+            targets.push(*to);
+          }
+        }
+      }
+      let closure = epsilon_closure(nfa, &targets);
+      if closure.is_empty() {
+        continue;
+      }
+      let to = *index.entry(closure.clone()).or_insert_with(|| {
+        let id = states.len();
+        states.push(DfaState { trans: vec![], accept: accept_of(nfa, &closure) });
+        worklist.push(closure.clone());
+        id
+      });
+      states[from].trans.push((atom, to));
+    }
+  }
+
+  Scanner { states, start: 0 }
+}
+
+/// Picks the accept tag with the lowest priority from a set of NFA states.
+fn accept_of(nfa: &Nfa, set: &[usize]) -> Option<TermId> {
+  set.iter()
+    .filter_map(|&s| nfa.states[s].accept)
+    .min_by_key(|tag| tag.priority)
+    .map(|tag| tag.term)
+}
+
+/// The set of states reachable from `seeds` through epsilon transitions,
+/// returned sorted and deduplicated so it can key the DFA state map.
+fn epsilon_closure(nfa: &Nfa, seeds: &[usize]) -> Vec<usize> {
+  let mut stack = seeds.to_vec();
+  let mut seen = vec![false; nfa.states.len()];
+  let mut out = vec![];
+  while let Some(s) = stack.pop() {
+    if seen[s] {
+      continue;
+    }
+    seen[s] = true;
+    out.push(s);
+    for &t in &nfa.states[s].eps {
+      stack.push(t);
+    }
+  }
+  out.sort_unstable();
+  out
+}
+
+/// Splits a collection of possibly-overlapping ranges into the minimal set of
+/// disjoint ranges that covers the same chars, so each can key one transition.
+fn atomic_ranges(ranges: &[CharRange]) -> Vec<CharRange> {
+  let mut bounds = vec![];
+  for r in ranges {
+    bounds.push(r.lo as u32);
+    bounds.push((r.hi as u32).saturating_add(1));
+  }
+  bounds.sort_unstable();
+  bounds.dedup();
+
+  let mut out = vec![];
+  for pair in bounds.windows(2) {
+    let lo = pair[0];
+    let hi = pair[1] - 1;
+    let (lo, hi) = match (char::from_u32(lo), char::from_u32(hi)) {
+      (Some(lo), Some(hi)) => (lo, hi),
+      _ => continue,
+    };
+    let atom = CharRange { lo, hi };
+    // keep only atoms that some input range actually covers
+    if ranges.iter().any(|r| r.contains(lo)) {
+      out.push(atom);
+    }
+  }
+  out
+}
+
+impl Grammar {
+  /// Builds a [`Scanner`] whose terminals are this grammar's tokens, each
+  /// recognized by the regex given for it in `regexes`. The regex list is
+  /// matched against [`tokens`](Grammar) by name, so a terminal's `TermId`
+  /// (its declaration order) also fixes its scanning priority: earlier tokens
+  /// win maximal-munch ties.
+  pub fn scanner(&self, regexes: &[(&str, &str)]) -> Result<Scanner, String> {
+    let mut ordered = Vec::with_capacity(self.tokens.len());
+    for token in &self.tokens {
+      match regexes.iter().find(|(name, _)| name == token) {
+        Some((_, re)) => ordered.push(*re),
+        None => return Err(format!("no regex declared for token '{}'", token)),
+      }
+    }
+    Scanner::new(&ordered)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn scan(regexes: &[&str], input: &str) -> Vec<(u32, String)> {
+    let scanner = Scanner::new(regexes).unwrap();
+    scanner.tokenize(input).unwrap().into_iter()
+      .map(|(term, span)| (term.0, input[span].to_owned()))
+      .collect()
+  }
+
+  #[test]
+  fn maximal_munch() {
+    // keyword vs identifier: longest match wins, ties go to the earlier regex
+    let toks = scan(&["if", "[a-z]+", "[ ]+"], "ifx if");
+    assert_eq!(toks, vec![
+      (1, "ifx".to_owned()),
+      (2, " ".to_owned()),
+      (0, "if".to_owned()),
+    ]);
+  }
+
+  #[test]
+  fn alternation_and_repetition() {
+    let toks = scan(&["(a|b)*c", "d"], "ababcd");
+    assert_eq!(toks, vec![
+      (0, "ababc".to_owned()),
+      (1, "d".to_owned()),
+    ]);
+  }
+
+  #[test]
+  fn char_class_and_escape() {
+    let toks = scan(&["[0-9]+", "\\+"], "12+34");
+    assert_eq!(toks, vec![
+      (0, "12".to_owned()),
+      (1, "+".to_owned()),
+      (0, "34".to_owned()),
+    ]);
+  }
+
+  #[test]
+  fn stuck_transition_is_an_error() {
+    let scanner = Scanner::new(&["[a-z]+"]).unwrap();
+    assert!(scanner.tokenize("abc1").is_err());
+  }
+}